@@ -0,0 +1,28 @@
+//! Packs the assets folder into the executable when the `embedded_assets` feature is on.
+
+#[allow(dead_code)]
+#[path = "src/utils/serdeflate.rs"]
+mod serdeflate;
+
+#[path = "src/assets/pack.rs"]
+mod pack;
+
+use std::{env, fs, path::PathBuf};
+
+use pack::AssetPack;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_none() {
+        return;
+    }
+    // This looks at everything in the folder
+    println!("cargo:rerun-if-changed=assets");
+
+    let root = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("assets");
+    let pack = AssetPack::from_dir(&root).expect("couldn't read the assets folder");
+    let zipped = serdeflate::binzip(&pack).expect("couldn't pack the assets");
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("assets.pack");
+    fs::write(out, zipped).expect("couldn't write the asset pack");
+}
@@ -0,0 +1,145 @@
+//! Reloading assets when they change on disk, in debug builds,
+//! so iterating on a shader or a level doesn't mean restarting the game.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ahash::AHashMap;
+use macroquad::prelude::{info, warn};
+
+use super::{levels::get_levels, Assets, Shaders, Textures, ASSETS_ROOT};
+
+/// How often to look at the files, in seconds
+const POLL_INTERVAL: f64 = 0.5;
+
+/// Folders under the assets root that get watched
+const WATCHED: &[&str] = &["levels", "shaders", "textures"];
+
+/// With the assets built into the executable, reloading would only read the same ones again,
+/// so there's no point watching the folder.
+const ENABLED: bool = !cfg!(feature = "embedded_assets");
+
+/// Keeps track of when each asset file was last modified.
+///
+/// There's no file watching crate, so this just looks every so often.
+pub struct Watcher {
+    modified: AHashMap<PathBuf, SystemTime>,
+    last_poll: f64,
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        let mut out = Self {
+            modified: AHashMap::new(),
+            last_poll: macroquad::time::get_time(),
+        };
+        if ENABLED {
+            out.scan();
+        } else {
+            info!("Not hot-reloading assets; they're built into the executable");
+        }
+        out
+    }
+
+    /// Get the files that changed since last time, if it's time to look again.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = macroquad::time::get_time();
+        if !ENABLED || now - self.last_poll < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = now;
+        self.scan()
+    }
+
+    /// Look at all the files, returning the ones that are new or changed.
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for dir in WATCHED {
+            list_files(&ASSETS_ROOT.join(dir), &mut files);
+        }
+
+        let mut changed = Vec::new();
+        for path in files {
+            let modified = match fs::metadata(&path).and_then(|meta| meta.modified()) {
+                Ok(it) => it,
+                Err(_) => continue,
+            };
+            if self.modified.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+}
+
+/// Put all the files in the directory, and the directories in it, into `out`.
+fn list_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(it) => it,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            list_files(&path, out);
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+impl Assets {
+    /// Load whatever kinds of assets these files are again, keeping everything else.
+    ///
+    /// If something doesn't load the old version is kept, so a half-saved shader
+    /// doesn't crash the game.
+    /// The old textures and shaders are never freed, but this is only for debugging.
+    pub async fn reload(&self, changed: &[PathBuf]) -> Assets {
+        let any_with = |exts: &[&str]| {
+            changed.iter().any(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map_or(false, |ext| exts.contains(&ext))
+            })
+        };
+
+        let (levels, level_errors) = if any_with(&["json"]) {
+            info!("Reloading levels");
+            get_levels().await
+        } else {
+            (self.levels.clone(), self.level_errors.clone())
+        };
+
+        let textures = if any_with(&["png"]) {
+            info!("Reloading textures");
+            Textures::init().await.unwrap_or_else(|err| {
+                warn!("Couldn't reload textures: {}", err);
+                self.textures.clone()
+            })
+        } else {
+            self.textures.clone()
+        };
+
+        let shaders = if any_with(&["vert", "frag"]) {
+            info!("Reloading shaders");
+            Shaders::init().await.unwrap_or_else(|err| {
+                warn!("Couldn't reload shaders: {}", err);
+                self.shaders.clone()
+            })
+        } else {
+            self.shaders.clone()
+        };
+
+        Assets {
+            textures,
+            sounds: self.sounds.clone(),
+            shaders,
+            levels,
+            level_errors,
+            packs: self.packs.clone(),
+        }
+    }
+}
@@ -1,46 +1,306 @@
-use macroquad::prelude::load_string;
+use macroquad::prelude::{load_string, warn};
 use serde::Deserialize;
 
-use crate::simulator::board::Board;
+use std::path::{Path, PathBuf};
 
-use super::ASSETS_ROOT;
+use crate::simulator::{board::Board, solutions::Metrics};
 
-pub(super) async fn get_levels() -> Vec<Level> {
-    let manifest = load_string(
-        ASSETS_ROOT
-            .join("levels/manifest.txt")
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
+use super::{
+    load_asset_string,
+    validate::{self, LevelError},
+    Assets,
+};
+
+/// Load all the built-in levels.
+pub(super) async fn get_levels() -> (Vec<Level>, Vec<LevelError>) {
+    load_levels_in(None).await
+}
+
+/// Load the built-in levels outside of macroquad, for tests.
+/// Nothing in the loader ever actually waits when it's reading from disk,
+/// so polling it once is enough.
+#[cfg(test)]
+pub(crate) fn load_builtin_levels() -> (Vec<Level>, Vec<LevelError>) {
+    use std::{
+        future::Future,
+        task::{Context, Poll, Waker},
+    };
+
+    let mut future = Box::pin(get_levels());
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(it) => it,
+        Poll::Pending => panic!("loading the levels had to wait"),
+    }
+}
+
+/// Load all the level packs the player has put in the packs folder.
+pub(super) async fn get_packs() -> Vec<LevelPack> {
+    let dir = match packs_dir() {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    // No folder just means no packs
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(it) => it,
+        Err(_) => return Vec::new(),
+    };
+    let mut names = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let mut packs = Vec::new();
+    for name in names {
+        let (levels, errors) = load_levels_in(Some((&name, &dir.join(&name)))).await;
+        packs.push(LevelPack {
+            name,
+            levels,
+            errors,
+        });
+    }
+    packs
+}
+
+/// Where players put level packs, one folder each.
+/// Each folder has a `manifest.json` and level files just like `assets/levels`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn packs_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join("levels"))
+}
+
+/// There's nowhere to put level packs on the web.
+#[cfg(target_arch = "wasm32")]
+pub fn packs_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Load all the levels in a manifest. If they're from a pack, give its name and folder;
+/// otherwise they're the built-in ones in the assets.
+///
+/// Levels that don't load or don't make sense are left out,
+/// and what's wrong with them is returned alongside.
+async fn load_levels_in(pack: Option<(&str, &Path)>) -> (Vec<Level>, Vec<LevelError>) {
+    let mut errors = Vec::new();
+    // Say what pack problems are in
+    let label = |name: &str| match pack {
+        Some((pack, _)) => format!("{}/{}.json", pack, name),
+        None => format!("{}.json", name),
+    };
+
+    let manifest = match load_level_file::<Manifest>(pack, &label("manifest"), "manifest").await {
+        Ok(it) => it,
+        Err(err) => {
+            warn!("{}", err);
+            return (Vec::new(), vec![err]);
+        }
+    };
 
     let mut levels = Vec::new();
-    for line in manifest.lines() {
-        let file = load_string(
-            ASSETS_ROOT
-                .join("levels")
-                .join(line)
+    for chapter in manifest.chapters {
+        for entry in chapter.levels {
+            let mut level =
+                match load_level_file::<Level>(pack, &label(&entry.file), &entry.file).await {
+                    Ok(it) => it,
+                    Err(err) => {
+                        warn!("{}", err);
+                        errors.push(err);
+                        continue;
+                    }
+                };
+            let problems = validate::check_level(&label(&entry.file), &level);
+            if !problems.is_empty() {
+                for err in problems.iter() {
+                    warn!("{}", err);
+                }
+                errors.extend(problems);
+                continue;
+            }
+
+            level.filename = entry.file;
+            level.pack = pack.map(|(pack, _)| pack.to_owned());
+            level.chapter = chapter.name.clone();
+            level.description = entry.description;
+            level.author = entry.author;
+            level.difficulty = entry.difficulty;
+            level.par = entry.par;
+            level.solves_to_unlock = entry.solves_to_unlock;
+            levels.push(level);
+        }
+    }
+
+    (levels, errors)
+}
+
+/// Load and parse a JSON file in the pack's folder, or the built-in levels if there's no pack,
+/// given its name without the `.json`.
+/// Errors say they're in the `label` file.
+async fn load_level_file<T: serde::de::DeserializeOwned>(
+    pack: Option<(&str, &Path)>,
+    label: &str,
+    name: &str,
+) -> Result<T, LevelError> {
+    let text = match pack {
+        Some((_, dir)) => load_string(
+            dir.join(name)
                 .with_extension("json")
                 .to_string_lossy()
                 .as_ref(),
         )
         .await
-        .unwrap();
-        let mut level: Level = serde_json::from_str(&file).unwrap();
-        level.filename = line.to_owned();
-        levels.push(level);
+        .map_err(|err| err.kind.to_string()),
+        None => load_asset_string(&format!("levels/{}.json", name))
+            .await
+            .map_err(|err| err.to_string()),
+    }
+    .map_err(|err| LevelError {
+        file: label.to_owned(),
+        path: String::new(),
+        message: format!("couldn't load it: {}", err),
+    })?;
+    validate::parse_json(label, &text)
+}
+
+/// A folder of levels the player put in the packs folder.
+#[derive(Clone)]
+pub struct LevelPack {
+    /// Name of the folder
+    pub name: String,
+    pub levels: Vec<Level>,
+    /// Problems with levels that meant they couldn't be loaded
+    pub errors: Vec<LevelError>,
+}
+
+/// Which list of levels is being played through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LevelSet {
+    BuiltIn,
+    /// Index into `Assets::packs`
+    Pack(usize),
+}
+
+impl LevelSet {
+    pub fn levels(self, assets: &Assets) -> &[Level] {
+        match self {
+            LevelSet::BuiltIn => &assets.levels,
+            LevelSet::Pack(idx) => &assets.packs[idx].levels,
+        }
     }
 
-    levels
+    pub fn errors(self, assets: &Assets) -> &[LevelError] {
+        match self {
+            LevelSet::BuiltIn => &assets.level_errors,
+            LevelSet::Pack(idx) => &assets.packs[idx].errors,
+        }
+    }
+}
+
+/// The order levels come in, and everything about them that isn't the board.
+#[derive(Deserialize)]
+struct Manifest {
+    chapters: Vec<Chapter>,
+}
+
+#[derive(Deserialize)]
+struct Chapter {
+    name: String,
+    levels: Vec<ManifestEntry>,
 }
 
 #[derive(Deserialize)]
+struct ManifestEntry {
+    /// Filename of the level, without the `.json`
+    file: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    author: String,
+    /// From 1 to 5
+    #[serde(default)]
+    difficulty: u8,
+    #[serde(default)]
+    par: Option<Metrics>,
+    #[serde(default)]
+    solves_to_unlock: usize,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct Level {
     #[serde(default)]
     pub filename: String,
     pub name: String,
 
+    // All these come from the manifest.
+    /// Name of the chapter this is in
+    #[serde(skip)]
+    pub chapter: String,
+    /// Briefing for the player
+    #[serde(skip)]
+    pub description: String,
+    #[serde(skip)]
+    pub author: String,
+    /// From 1 to 5, or 0 if nobody said
+    #[serde(skip)]
+    pub difficulty: u8,
+    /// Metrics a good solution should match
+    #[serde(skip)]
+    pub par: Option<Metrics>,
+    /// How many levels have to be solved before this one can be played.
+    #[serde(skip)]
+    pub solves_to_unlock: usize,
+    /// Name of the pack this is from, or `None` if it's built in
+    #[serde(skip)]
+    pub pack: Option<String>,
+
     #[serde(flatten)]
     pub starting_board: Board,
 }
+
+impl Level {
+    /// Key for this level's solution in the profile.
+    /// Levels from packs are namespaced so they can't collide with the built-in ones.
+    pub fn key(&self) -> String {
+        match &self.pack {
+            Some(pack) => format!("custom/{}/{}", pack, self.filename),
+            None => self.filename.clone(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_builtin_level_loads() {
+        let (levels, errors) = load_builtin_levels();
+        assert!(
+            errors.is_empty(),
+            "problems with the built-in levels:\n{}",
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        let manifest: Manifest = serde_json::from_slice(
+            &std::fs::read(super::super::ASSETS_ROOT.join("levels/manifest.json")).unwrap(),
+        )
+        .unwrap();
+        let listed = manifest
+            .chapters
+            .into_iter()
+            .flat_map(|chapter| chapter.levels)
+            .map(|entry| entry.file)
+            .collect::<Vec<_>>();
+        let loaded = levels
+            .iter()
+            .map(|level| level.filename.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(listed, loaded);
+    }
+}
@@ -1,10 +1,20 @@
 #![allow(clippy::eval_order_dependence)]
 
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+mod hot_reload;
 mod levels;
-pub use levels::Level;
+#[cfg(feature = "embedded_assets")]
+mod pack;
+mod validate;
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub use hot_reload::Watcher;
+#[cfg(test)]
+pub(crate) use levels::load_builtin_levels;
+pub use levels::{packs_dir, Level, LevelPack, LevelSet};
+pub use validate::LevelError;
 
 use macroquad::{
-    audio::{load_sound, Sound},
+    audio::{load_sound_from_bytes, Sound},
     miniquad::*,
     prelude::*,
 };
@@ -12,27 +22,35 @@ use once_cell::sync::Lazy;
 
 use std::path::PathBuf;
 
-use self::levels::get_levels;
+use self::levels::{get_levels, get_packs};
 
 pub struct Assets {
     pub textures: Textures,
     pub sounds: Sounds,
     pub shaders: Shaders,
     pub levels: Vec<Level>,
+    /// Problems with levels that meant they couldn't be loaded
+    pub level_errors: Vec<LevelError>,
+    /// Levels the player added themselves
+    pub packs: Vec<LevelPack>,
 }
 
 impl Assets {
     pub async fn init() -> Self {
         info!("Initing assets");
+        let (levels, level_errors) = get_levels().await;
         Self {
-            textures: Textures::init().await,
+            textures: Textures::init().await.unwrap(),
             sounds: Sounds::init().await,
-            shaders: Shaders::init().await,
-            levels: get_levels().await,
+            shaders: Shaders::init().await.unwrap(),
+            levels,
+            level_errors,
+            packs: get_packs().await,
         }
     }
 }
 
+#[derive(Clone)]
 pub struct Textures {
     pub title_banner: Texture2D,
     pub font: Texture2D,
@@ -46,24 +64,25 @@ pub struct Textures {
 }
 
 impl Textures {
-    async fn init() -> Self {
+    async fn init() -> anyhow::Result<Self> {
         info!("> Textures");
         let out = Self {
-            title_banner: texture("title/banner").await,
-            font: texture("ui/font").await,
+            title_banner: texture("title/banner").await?,
+            font: texture("ui/font").await?,
 
-            cable_atlas: texture("cable_atlas").await,
-            port_atlas: texture("port_atlas").await,
-            error_atlas: texture("error_atlas").await,
+            cable_atlas: texture("cable_atlas").await?,
+            port_atlas: texture("port_atlas").await?,
+            error_atlas: texture("error_atlas").await?,
 
-            hologram_9patch: texture("ui/hologram_9patch").await,
-            you_win: texture("ui/you_win").await,
+            hologram_9patch: texture("ui/hologram_9patch").await?,
+            you_win: texture("ui/you_win").await?,
         };
         info!("> End Textures");
-        out
+        Ok(out)
     }
 }
 
+#[derive(Clone)]
 pub struct Sounds {
     pub title_jingle: Sound,
 }
@@ -77,6 +96,7 @@ impl Sounds {
     }
 }
 
+#[derive(Clone)]
 pub struct Shaders {
     pub space: Material,
     pub hologram: Material,
@@ -84,9 +104,9 @@ pub struct Shaders {
 }
 
 impl Shaders {
-    async fn init() -> Self {
+    async fn init() -> anyhow::Result<Self> {
         info!("> Shaders");
-        Self {
+        Ok(Self {
             space: material_vert_frag(
                 "standard",
                 "space",
@@ -103,7 +123,7 @@ impl Shaders {
                     },
                 },
             )
-            .await,
+            .await?,
             hologram: material_vert_frag(
                 "standard",
                 "hologram",
@@ -120,7 +140,7 @@ impl Shaders {
                     },
                 },
             )
-            .await,
+            .await?,
             cables: material_vert_frag(
                 "standard",
                 "cables",
@@ -140,75 +160,148 @@ impl Shaders {
                     },
                 },
             )
-            .await,
-        }
+            .await?,
+        })
     }
 }
 
+/// All the assets, built into the executable by `build.rs`.
+#[cfg(feature = "embedded_assets")]
+static EMBEDDED_ASSETS: Lazy<pack::AssetPack> = Lazy::new(|| {
+    let zipped = include_bytes!(concat!(env!("OUT_DIR"), "/assets.pack"));
+    crate::utils::serdeflate::unbinzip(zipped).unwrap()
+});
+
 /// Path to the assets root
+#[cfg_attr(
+    all(feature = "embedded_assets", not(debug_assertions)),
+    allow(dead_code)
+)]
 static ASSETS_ROOT: Lazy<PathBuf> = Lazy::new(|| {
     if cfg!(target_arch = "wasm32") {
         PathBuf::from("./assets")
     } else if cfg!(debug_assertions) {
         PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"))
     } else {
-        todo!("assets path for release hasn't been finalized yet ;-;")
+        release_assets_root()
     }
 });
 
-async fn texture(path: &str) -> Texture2D {
-    let with_extension = path.to_owned() + ".png";
-    let tex = load_texture(
-        ASSETS_ROOT
-            .join("textures")
-            .join(with_extension)
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
+/// Environment variable that says where the assets are, overriding everything else.
+#[cfg(not(target_arch = "wasm32"))]
+const ASSETS_ENV_VAR: &str = "SPACECABLE_ASSETS";
+
+/// Find the assets folder for a release build. In order, this looks:
+///
+/// - wherever the `SPACECABLE_ASSETS` environment variable says
+/// - in an `assets` folder next to the executable
+/// - in `spacecable/assets` in the user's data folder, and then in each of the `XDG_DATA_DIRS`
+#[cfg(not(target_arch = "wasm32"))]
+fn release_assets_root() -> PathBuf {
+    if let Some(path) = std::env::var_os(ASSETS_ENV_VAR) {
+        // If they went to the trouble of saying, don't second-guess it
+        return PathBuf::from(path);
+    }
+
+    let mut candidates = Vec::new();
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(ToOwned::to_owned))
+    {
+        candidates.push(exe_dir.join("assets"));
+    }
+    if let Some(data_dir) = dirs::data_dir() {
+        candidates.push(data_dir.join(env!("CARGO_PKG_NAME")).join("assets"));
+    }
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+    for dir in data_dirs.split(':').filter(|dir| !dir.is_empty()) {
+        candidates.push(
+            PathBuf::from(dir)
+                .join(env!("CARGO_PKG_NAME"))
+                .join("assets"),
+        );
+    }
+
+    match candidates.iter().find(|path| path.is_dir()) {
+        Some(found) => {
+            info!("Using assets at {}", found.display());
+            found.clone()
+        }
+        None => {
+            let tried = candidates
+                .iter()
+                .map(|path| format!("\n  {}", path.display()))
+                .collect::<String>();
+            panic!(
+                "Couldn't find the assets folder! Set {} to where it is. Looked in:{}",
+                ASSETS_ENV_VAR, tried
+            )
+        }
+    }
+}
+
+/// The web build always has its assets next to it.
+#[cfg(target_arch = "wasm32")]
+fn release_assets_root() -> PathBuf {
+    PathBuf::from("./assets")
+}
+
+/// Load a file in the assets, given its path from the assets root like `shaders/space.frag`.
+///
+/// With the `embedded_assets` feature this comes out of the executable;
+/// otherwise it's loaded from `ASSETS_ROOT`.
+async fn load_asset(path: &str) -> anyhow::Result<Vec<u8>> {
+    #[cfg(feature = "embedded_assets")]
+    {
+        EMBEDDED_ASSETS
+            .get(path)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| anyhow::anyhow!("{} isn't in the embedded assets", path))
+    }
+    // There's no macroquad context to load through in tests
+    #[cfg(all(test, not(feature = "embedded_assets")))]
+    {
+        Ok(std::fs::read(ASSETS_ROOT.join(path))?)
+    }
+    #[cfg(all(not(test), not(feature = "embedded_assets")))]
+    {
+        Ok(load_file(ASSETS_ROOT.join(path).to_string_lossy().as_ref()).await?)
+    }
+}
+
+/// Load a text file in the assets, given its path from the assets root.
+async fn load_asset_string(path: &str) -> anyhow::Result<String> {
+    Ok(String::from_utf8(load_asset(path).await?)?)
+}
+
+async fn texture(path: &str) -> anyhow::Result<Texture2D> {
+    let bytes = load_asset(&format!("textures/{}.png", path)).await?;
+    let img = image::load_from_memory(&bytes)?.to_rgba8();
+    let tex = Texture2D::from_rgba8(img.width() as u16, img.height() as u16, &img);
     tex.set_filter(FilterMode::Nearest);
-    tex
+    Ok(tex)
 }
 
 async fn sound(path: &str) -> Sound {
-    let with_extension = path.to_owned() + ".ogg";
-    load_sound(
-        ASSETS_ROOT
-            .join("sounds")
-            .join(with_extension)
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap()
-}
-
-async fn material_vert_frag(vert_stub: &str, frag_stub: &str, params: MaterialParams) -> Material {
-    let full_stub = ASSETS_ROOT.join("shaders");
-    let vert = load_string(
-        full_stub
-            .join(vert_stub)
-            .with_extension("vert")
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
-    let frag = load_string(
-        full_stub
-            .join(frag_stub)
-            .with_extension("frag")
-            .to_string_lossy()
-            .as_ref(),
-    )
-    .await
-    .unwrap();
-    let out = load_material(&vert, &frag, params).unwrap();
+    let bytes = load_asset(&format!("sounds/{}.ogg", path)).await.unwrap();
+    load_sound_from_bytes(&bytes).await.unwrap()
+}
+
+async fn material_vert_frag(
+    vert_stub: &str,
+    frag_stub: &str,
+    params: MaterialParams,
+) -> anyhow::Result<Material> {
+    let vert = load_asset_string(&format!("shaders/{}.vert", vert_stub)).await?;
+    let frag = load_asset_string(&format!("shaders/{}.frag", frag_stub)).await?;
+    let out = load_material(&vert, &frag, params)?;
     info!("> > Loaded {}.vert, {}.frag", vert_stub, frag_stub);
-    out
+    Ok(out)
 }
 
-async fn material(path_stub: &str, params: MaterialParams) -> Material {
+async fn material(path_stub: &str, params: MaterialParams) -> anyhow::Result<Material> {
     material_vert_frag(path_stub, path_stub, params).await
 }
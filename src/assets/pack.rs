@@ -0,0 +1,54 @@
+//! A bundle of the files in the assets folder the game loads, so they can be built into the executable.
+//!
+//! `build.rs` makes this with `AssetPack::from_dir` and zips it with `serdeflate`
+//! when the `embedded_assets` feature is on; the game unzips it on startup.
+
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// Extensions of the files the game loads.
+/// Everything else in the folder, like source art and music projects, is left out.
+const PACKED: &[&str] = &["png", "ogg", "frag", "vert", "json"];
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct AssetPack {
+    /// Contents of each file, keyed by its path from the assets folder,
+    /// with `/` between the parts like `textures/ui/font.png`.
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl AssetPack {
+    /// Read every file the game loads in the folder, and the folders in it, into a pack.
+    #[allow(dead_code)]
+    pub fn from_dir(root: &Path) -> io::Result<Self> {
+        let mut pack = Self::default();
+        pack.add_dir(root, "")?;
+        Ok(pack)
+    }
+
+    fn add_dir(&mut self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                self.add_dir(&entry.path(), &format!("{}/", name))?;
+            } else if entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .filter(|ext| PACKED.contains(ext))
+                .is_some()
+            {
+                self.files.insert(name, fs::read(entry.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the contents of the file at the path from the assets folder.
+    #[allow(dead_code)]
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+}
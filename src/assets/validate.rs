@@ -0,0 +1,339 @@
+//! Checking level files over before they get played,
+//! so a typo in one level doesn't take the whole game down.
+
+use std::fmt;
+
+use cogs_gamedev::grids::ICoord;
+use serde::de::DeserializeOwned;
+
+use crate::simulator::transport::{Port, Resource};
+
+use super::Level;
+
+/// Something wrong with a level file.
+#[derive(Debug, Clone)]
+pub struct LevelError {
+    /// File it's in, like `level1.json`
+    pub file: String,
+    /// Where in the JSON it is, like `connectors[1].ports[3]`.
+    /// Empty if it's about the whole file.
+    pub path: String,
+    pub message: String,
+}
+
+impl LevelError {
+    fn new(file: &str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            file: file.to_owned(),
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Get this ready to show the player, wrapped to so many characters across.
+    pub fn describe(&self, chars_across: usize) -> String {
+        let text = self.to_string().to_uppercase();
+        let chars = text.chars().collect::<Vec<_>>();
+        chars
+            .chunks(chars_across)
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{} at {}: {}", self.file, self.path, self.message)
+        }
+    }
+}
+
+/// Parse some JSON, saying where it went wrong if it didn't work.
+pub fn parse_json<T: DeserializeOwned>(file: &str, text: &str) -> Result<T, LevelError> {
+    serde_json::from_str(text).map_err(|err| {
+        LevelError::new(
+            file,
+            json_path_at(text, err.line(), err.column()),
+            err.to_string(),
+        )
+    })
+}
+
+/// Make sure the level makes sense as a puzzle.
+/// This returns every problem instead of stopping at the first one.
+pub fn check_level(file: &str, level: &Level) -> Vec<LevelError> {
+    let mut errors = Vec::new();
+    let board = &level.starting_board;
+
+    if board.width < 1 {
+        errors.push(LevelError::new(file, "width", "width has to be at least 1"));
+    }
+    if board.height < 1 {
+        errors.push(LevelError::new(
+            file,
+            "height",
+            "height has to be at least 1",
+        ));
+    }
+
+    // How many more sources than sinks each resource has
+    let mut balance: Vec<(Resource, isize)> = Vec::new();
+    for (conn_idx, conn) in board.connectors.iter().enumerate() {
+        if conn.slider.len() != conn.ports.len() {
+            errors.push(LevelError::new(
+                file,
+                format!("connectors[{}].slider", conn_idx),
+                format!(
+                    "there are {} sliders but {} ports",
+                    conn.slider.len(),
+                    conn.ports.len()
+                ),
+            ));
+        }
+
+        // West and east connectors go down the side, north and south ones across
+        let (edge, edge_len) = if conn.side.is_horizontal() {
+            ("height", board.height)
+        } else {
+            ("width", board.width)
+        };
+        if conn.offset + conn.ports.len() > edge_len {
+            errors.push(LevelError::new(
+                file,
+                format!("connectors[{}].offset", conn_idx),
+                format!(
+                    "the ports run off the edge; {} from {} goes past the {} of {}",
+                    conn.ports.len(),
+                    conn.offset,
+                    edge,
+                    edge_len
+                ),
+            ));
+        }
+
+        for port in conn.ports.iter().flatten() {
+            let (res, delta) = match port {
+                Port::Source(res) => (res, 1),
+                Port::Sink(res) => (res, -1),
+            };
+            match balance.iter_mut().find(|(seen, _)| seen == res) {
+                Some((_, count)) => *count += delta,
+                None => balance.push((res.clone(), delta)),
+            }
+        }
+    }
+    for (res, count) in balance {
+        if count != 0 {
+            let (more, fewer) = if count > 0 {
+                ("sources", "sinks")
+            } else {
+                ("sinks", "sources")
+            };
+            errors.push(LevelError::new(
+                file,
+                "connectors",
+                format!(
+                    "{} has {} more {} than {}",
+                    res.describe().to_lowercase(),
+                    count.abs(),
+                    more,
+                    fewer
+                ),
+            ));
+        }
+    }
+
+    let mut off_board = board
+        .cables
+        .keys()
+        .filter(|pos| !board.is_in_cable_area(**pos))
+        .copied()
+        .collect::<Vec<ICoord>>();
+    off_board.sort_by_key(|pos| (pos.y, pos.x));
+    for pos in off_board {
+        errors.push(LevelError::new(
+            file,
+            "cables",
+            format!("the cable at ({}, {}) isn't on the board", pos.x, pos.y),
+        ));
+    }
+
+    errors
+}
+
+/// Work out the path to the value at the given line and column,
+/// like `connectors[1].ports[3]`.
+///
+/// `serde_json` only tells us the line and column of an error,
+/// so this goes over the text up to there keeping track of where it is.
+/// The line and column are 1-indexed, like `serde_json` gives them.
+fn json_path_at(text: &str, line: usize, column: usize) -> String {
+    enum Frame {
+        Object {
+            key: Option<String>,
+            expecting_key: bool,
+        },
+        Array(usize),
+    }
+
+    // Turn the line and column into a byte offset
+    let end = text
+        .split('\n')
+        .take(line.saturating_sub(1))
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        + column;
+    let end = end.min(text.len());
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut bytes = text.as_bytes()[..end].iter().copied();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'"' => {
+                let mut string = Vec::new();
+                while let Some(b) = bytes.next() {
+                    match b {
+                        b'"' => break,
+                        b'\\' => string.extend(bytes.next()),
+                        _ => string.push(b),
+                    }
+                }
+                if let Some(Frame::Object {
+                    key,
+                    expecting_key: true,
+                }) = stack.last_mut()
+                {
+                    *key = Some(String::from_utf8_lossy(&string).into_owned());
+                }
+            }
+            b'{' => stack.push(Frame::Object {
+                key: None,
+                expecting_key: true,
+            }),
+            b'[' => stack.push(Frame::Array(0)),
+            b'}' | b']' => {
+                stack.pop();
+            }
+            b':' => {
+                if let Some(Frame::Object { expecting_key, .. }) = stack.last_mut() {
+                    *expecting_key = false;
+                }
+            }
+            b',' => match stack.last_mut() {
+                Some(Frame::Object { expecting_key, .. }) => *expecting_key = true,
+                Some(Frame::Array(idx)) => *idx += 1,
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    let mut path = String::new();
+    for frame in stack {
+        match frame {
+            Frame::Object { key: Some(key), .. } => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&key);
+            }
+            Frame::Object { key: None, .. } => {}
+            Frame::Array(idx) => path.push_str(&format!("[{}]", idx)),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Find the path where the `|` is, as if `serde_json` had an error on the character after it.
+    fn path_at_marker(marked: &str) -> String {
+        let idx = marked.find('|').expect("no marker");
+        let text = marked.replacen('|', "", 1);
+        let line_start = text[..idx].rfind('\n').map(|nl| nl + 1).unwrap_or(0);
+        let line = text[..idx].matches('\n').count() + 1;
+        let column = idx - line_start + 1;
+        json_path_at(&text, line, column)
+    }
+
+    #[test]
+    fn paths_at_markers() {
+        let table: &[(&str, &str)] = &[
+            ("|{}", ""),
+            (r#"{"width": |5}"#, "width"),
+            (r#"{"a": [[1, 2], [3, |4]]}"#, "a[1][1]"),
+            (r#"{"a": [[1, 2], [3, 4]], "b": |0}"#, "b"),
+            (r#"[{"a": 1}, {"b": [|0]}]"#, "[1].b[0]"),
+            (r#"{"name": "say \"hi\", [ok]", "width": |5}"#, "width"),
+            (r#"{"a\"b": |1}"#, "a\"b"),
+            (
+                "{\r\n    \"connectors\": [\r\n        {\"side\": |\"Up\"}\r\n    ]\r\n}",
+                "connectors[0].side",
+            ),
+        ];
+        for (marked, expected) in table {
+            assert_eq!(&path_at_marker(marked), expected, "in {:?}", marked);
+        }
+    }
+
+    #[test]
+    fn paths_from_real_errors() {
+        let table: &[(&str, &str)] = &[
+            // Right at the start
+            ("nope", ""),
+            ("", ""),
+            // Cut off at the end
+            (
+                r#"{"connectors": [{"side": "West", "ports": [null"#,
+                "connectors[0].ports[0]",
+            ),
+            (r#"{"name": "LEVEL", "width": 5"#, "width"),
+            // Somewhere in the middle
+            (r#"{"name": "LEVEL", "width": 5 "height": 3}"#, "width"),
+        ];
+        for (text, expected) in table {
+            let err = parse_json::<Level>("test.json", text)
+                .err()
+                .unwrap_or_else(|| panic!("{:?} parsed", text));
+            assert_eq!(&err.path, expected, "in {:?}", text);
+        }
+    }
+
+    #[test]
+    fn connectors_cant_run_off_the_edge() {
+        let level = |side: &str, offset: usize| {
+            parse_json::<Level>(
+                "test.json",
+                &format!(
+                    r#"{{
+                        "name": "TEST",
+                        "width": 4,
+                        "height": 2,
+                        "connectors": [
+                            {{ "side": "{}", "offset": {}, "ports": [null, null], "slider": [false, false] }}
+                        ]
+                    }}"#,
+                    side, offset
+                ),
+            )
+            .unwrap()
+        };
+        let paths = |level: &Level| {
+            check_level("test.json", level)
+                .into_iter()
+                .map(|err| err.path)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(paths(&level("West", 0)).is_empty());
+        assert_eq!(paths(&level("West", 1)), vec!["connectors[0].offset"]);
+        assert!(paths(&level("North", 2)).is_empty());
+        assert_eq!(paths(&level("South", 3)), vec!["connectors[0].offset"]);
+    }
+}
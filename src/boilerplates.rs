@@ -1,5 +1,7 @@
 use crate::{assets::Assets, controls::InputSubscriber, HEIGHT, WIDTH};
 
+use std::path::PathBuf;
+
 use macroquad::{
     camera::{set_camera, Camera2D},
     prelude::{render_target, vec2, FilterMode, Texture2D},
@@ -23,6 +25,10 @@ pub trait Gamemode {
     /// When a `Transition` finishes and things are popped off to reveal this gamemode,
     /// this function is called.
     fn on_resume(&mut self, assets: &Assets) {}
+
+    /// In debug builds, when files change on disk and the assets get reloaded,
+    /// this function is called on every gamemode in the stack, bottom to top.
+    fn on_assets_reloaded(&mut self, assets: &Assets, changed: &[PathBuf]) {}
 }
 
 /// Data on how to draw a state
@@ -0,0 +1,130 @@
+//! Gamepad input, fed into the same handler as the keyboard and mouse.
+
+use serde::{Deserialize, Serialize};
+
+/// Buttons on a gamepad, named by where they are on the controller
+/// so it doesn't matter whose brand of gamepad it is.
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+pub(super) use backend::poll;
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+mod backend {
+    use gilrs::{Axis, Button, EventType, Gilrs};
+    use macroquad::prelude::warn;
+
+    use std::cell::RefCell;
+
+    use super::GamepadButton;
+
+    /// How far the stick has to be pushed before it counts.
+    const STICK_DEADZONE: f32 = 0.25;
+
+    thread_local! {
+        /// Gilrs can't be sent between threads, so it lives on whichever thread polls it.
+        /// This is `None` if gamepads couldn't be started.
+        static BACKEND: RefCell<Option<GamepadBackend>> = RefCell::new(GamepadBackend::new());
+    }
+
+    /// Go through all the gamepad events since last time,
+    /// calling `on_button` with each button and whether it was pressed or released.
+    ///
+    /// Returns the position of the left stick.
+    pub fn poll(on_button: impl FnMut(GamepadButton, bool)) -> (f32, f32) {
+        BACKEND.with(|backend| match &mut *backend.borrow_mut() {
+            Some(backend) => {
+                backend.poll(on_button);
+                backend.stick()
+            }
+            None => (0.0, 0.0),
+        })
+    }
+
+    /// Polls all the connected gamepads.
+    struct GamepadBackend {
+        gilrs: Gilrs,
+        /// Position of the left stick, with +Y downwards like the screen.
+        stick: (f32, f32),
+    }
+
+    impl GamepadBackend {
+        /// Returns `None` if gamepads aren't supported here.
+        fn new() -> Option<Self> {
+            match Gilrs::new() {
+                Ok(gilrs) => Some(Self {
+                    gilrs,
+                    stick: (0.0, 0.0),
+                }),
+                Err(oh_no) => {
+                    warn!("Couldn't start gamepad support!\n{:?}", oh_no);
+                    None
+                }
+            }
+        }
+
+        fn poll(&mut self, mut on_button: impl FnMut(GamepadButton, bool)) {
+            while let Some(event) = self.gilrs.next_event() {
+                match event.event {
+                    EventType::ButtonPressed(button, _) => {
+                        if let Some(button) = convert_button(button) {
+                            on_button(button, true);
+                        }
+                    }
+                    EventType::ButtonReleased(button, _) => {
+                        if let Some(button) = convert_button(button) {
+                            on_button(button, false);
+                        }
+                    }
+                    EventType::AxisChanged(Axis::LeftStickX, value, _) => self.stick.0 = value,
+                    // gilrs has +Y up
+                    EventType::AxisChanged(Axis::LeftStickY, value, _) => self.stick.1 = -value,
+                    EventType::Disconnected => self.stick = (0.0, 0.0),
+                    _ => {}
+                }
+            }
+        }
+
+        /// Position of the left stick, or zero if it's inside the deadzone.
+        fn stick(&self) -> (f32, f32) {
+            let (x, y) = self.stick;
+            if x.hypot(y) < STICK_DEADZONE {
+                (0.0, 0.0)
+            } else {
+                (x, y)
+            }
+        }
+    }
+
+    fn convert_button(button: Button) -> Option<GamepadButton> {
+        Some(match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::North => GamepadButton::North,
+            Button::West => GamepadButton::West,
+            Button::LeftTrigger => GamepadButton::LeftBumper,
+            Button::RightTrigger => GamepadButton::RightBumper,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            _ => return None,
+        })
+    }
+}
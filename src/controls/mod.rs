@@ -0,0 +1,326 @@
+mod gamepad;
+mod remote;
+
+pub use gamepad::GamepadButton;
+
+use cogs_gamedev::controls::EventInputHandler;
+use enum_map::Enum;
+use macroquad::{
+    miniquad::{self, Context, KeyMods},
+    prelude::{
+        utils::{register_input_subscriber, repeat_all_miniquad_input},
+        KeyCode, MouseButton,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::utils::profile::Profile;
+
+use self::remote::{KeyCodeDef, MouseButtonDef};
+
+/// Set when the keymap in the profile changes, so the subscriber knows to reload it.
+static KEYMAP_DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// The controls
+#[derive(Enum, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Control {
+    Select,
+
+    Up,
+    Down,
+    Left,
+    Right,
+
+    Start,
+    StepOnce,
+
+    Escape,
+    Ctrl,
+
+    Copy,
+    Cut,
+    Paste,
+    Rotate,
+    Mirror,
+    Retype,
+    SwapLayers,
+
+    /// Hold to drag the board around
+    Pan,
+    Speed,
+}
+
+impl Control {
+    /// Every control, in the order they're shown on the options screen.
+    pub const ALL: [Control; 18] = [
+        Control::Select,
+        Control::Up,
+        Control::Down,
+        Control::Left,
+        Control::Right,
+        Control::Start,
+        Control::StepOnce,
+        Control::Speed,
+        Control::Escape,
+        Control::Ctrl,
+        Control::Copy,
+        Control::Cut,
+        Control::Paste,
+        Control::Rotate,
+        Control::Mirror,
+        Control::Retype,
+        Control::SwapLayers,
+        Control::Pan,
+    ];
+
+    /// Name to show the player.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Select => "SELECT",
+            Control::Up => "UP",
+            Control::Down => "DOWN",
+            Control::Left => "LEFT",
+            Control::Right => "RIGHT",
+            Control::Start => "START",
+            Control::StepOnce => "STEP ONCE",
+            Control::Speed => "SPEED",
+            Control::Escape => "BACK",
+            Control::Ctrl => "ADVANCED",
+            Control::Copy => "COPY",
+            Control::Cut => "CUT",
+            Control::Paste => "PASTE",
+            Control::Rotate => "ROTATE",
+            Control::Mirror => "MIRROR",
+            Control::Retype => "PIPE/WIRE",
+            Control::SwapLayers => "SWAP LAYERS",
+            Control::Pan => "PAN",
+        }
+    }
+}
+
+/// Combo keycode and mouse button code
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum InputCode {
+    Key(#[serde(with = "KeyCodeDef")] KeyCode),
+    Mouse(#[serde(with = "MouseButtonDef")] MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl InputCode {
+    /// Name to show the player.
+    pub fn name(&self) -> String {
+        match self {
+            InputCode::Key(key) => format!("{:?}", key).to_uppercase(),
+            InputCode::Mouse(button) => format!("MOUSE {:?}", button).to_uppercase(),
+            InputCode::Gamepad(button) => format!("PAD {:?}", button).to_uppercase(),
+        }
+    }
+
+    /// Are these both keys, both mouse buttons, or both gamepad buttons?
+    pub fn same_device(&self, other: &InputCode) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// Event handler to hook into miniquad and get inputs
+pub struct InputSubscriber {
+    controls: EventInputHandler<InputCode, Control>,
+    subscriber_id: usize,
+    /// The raw input that was pressed this frame, if any.
+    /// Used for rebinding.
+    last_input: Option<InputCode>,
+    /// Position of the gamepad's left stick
+    stick: (f32, f32),
+    /// How far the mouse wheel scrolled this frame
+    wheel: f32,
+}
+
+impl InputSubscriber {
+    pub fn new() -> Self {
+        // the science kid
+        let sid = register_input_subscriber();
+
+        InputSubscriber {
+            controls: EventInputHandler::new(Profile::get().keymap.clone()),
+            subscriber_id: sid,
+            last_input: None,
+            stick: (0.0, 0.0),
+            wheel: 0.0,
+        }
+    }
+
+    /// Tell the subscriber to reload the keymap from the profile on its next update.
+    pub fn mark_keymap_dirty() {
+        KEYMAP_DIRTY.store(true, Ordering::SeqCst);
+    }
+
+    /// The raw input that was pressed this frame, if any.
+    pub fn last_input(&self) -> Option<InputCode> {
+        self.last_input
+    }
+
+    /// Position of the gamepad's left stick from -1 to 1, with +Y downwards.
+    /// This is zero if it's not being pushed or there's no gamepad.
+    pub fn stick(&self) -> (f32, f32) {
+        self.stick
+    }
+
+    /// How far the mouse wheel scrolled this frame; positive is away from the player.
+    pub fn wheel(&self) -> f32 {
+        self.wheel
+    }
+
+    pub fn default_controls() -> HashMap<InputCode, Control> {
+        let mut controls = HashMap::new();
+
+        // Put your controls here
+        for (code, control) in [
+            (KeyCode::Enter, Control::Select),
+            //
+            (KeyCode::W, Control::Up),
+            (KeyCode::Up, Control::Up),
+            (KeyCode::A, Control::Left),
+            (KeyCode::Left, Control::Left),
+            (KeyCode::S, Control::Down),
+            (KeyCode::Down, Control::Down),
+            (KeyCode::D, Control::Right),
+            (KeyCode::Right, Control::Right),
+            //
+            (KeyCode::Space, Control::Start),
+            (KeyCode::Tab, Control::StepOnce),
+            (KeyCode::F, Control::Speed),
+            //
+            (KeyCode::Escape, Control::Escape),
+            (KeyCode::LeftControl, Control::Ctrl),
+            (KeyCode::RightControl, Control::Ctrl),
+            //
+            (KeyCode::C, Control::Copy),
+            (KeyCode::X, Control::Cut),
+            (KeyCode::V, Control::Paste),
+            (KeyCode::R, Control::Rotate),
+            (KeyCode::M, Control::Mirror),
+            (KeyCode::T, Control::Retype),
+            (KeyCode::L, Control::SwapLayers),
+        ] {
+            controls.insert(InputCode::Key(code), control);
+        }
+        controls.insert(InputCode::Mouse(MouseButton::Left), Control::Select);
+        controls.insert(InputCode::Mouse(MouseButton::Middle), Control::Pan);
+
+        for (button, control) in [
+            (GamepadButton::South, Control::Select),
+            //
+            (GamepadButton::DPadUp, Control::Up),
+            (GamepadButton::DPadLeft, Control::Left),
+            (GamepadButton::DPadDown, Control::Down),
+            (GamepadButton::DPadRight, Control::Right),
+            //
+            (GamepadButton::Start, Control::Start),
+            (GamepadButton::North, Control::StepOnce),
+            (GamepadButton::West, Control::Speed),
+            //
+            (GamepadButton::East, Control::Escape),
+            (GamepadButton::LeftBumper, Control::Ctrl),
+        ] {
+            controls.insert(InputCode::Gamepad(button), control);
+        }
+
+        controls
+    }
+
+    /// Controls added since the keymap was saved have nothing bound to them,
+    /// so give them their default inputs (if those inputs aren't taken).
+    pub fn fill_in_default_controls(keymap: &mut HashMap<InputCode, Control>) {
+        let unbound = Control::ALL
+            .iter()
+            .filter(|control| !keymap.values().any(|bound| bound == *control))
+            .copied()
+            .collect::<Vec<_>>();
+        for (input, control) in Self::default_controls() {
+            if unbound.contains(&control) {
+                keymap.entry(input).or_insert(control);
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        if KEYMAP_DIRTY.swap(false, Ordering::SeqCst) {
+            self.controls = EventInputHandler::new(Profile::get().keymap.clone());
+        }
+
+        self.last_input = None;
+        self.wheel = 0.0;
+        repeat_all_miniquad_input(self, self.subscriber_id);
+
+        #[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+        {
+            let controls = &mut self.controls;
+            let last_input = &mut self.last_input;
+            self.stick = gamepad::poll(|button, down| {
+                let code = InputCode::Gamepad(button);
+                if down {
+                    controls.input_down(code);
+                    *last_input = Some(code);
+                } else {
+                    controls.input_up(code);
+                }
+            });
+        }
+
+        self.controls.update();
+    }
+}
+
+impl std::ops::Deref for InputSubscriber {
+    type Target = EventInputHandler<InputCode, Control>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.controls
+    }
+}
+
+impl miniquad::EventHandler for InputSubscriber {
+    fn update(&mut self, _ctx: &mut Context) {}
+
+    fn draw(&mut self, _ctx: &mut Context) {}
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if !repeat {
+            self.controls.input_down(InputCode::Key(keycode));
+            self.last_input = Some(InputCode::Key(keycode));
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
+        self.controls.input_up(InputCode::Key(keycode));
+    }
+
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) {
+        self.controls.input_down(InputCode::Mouse(button));
+        self.last_input = Some(InputCode::Mouse(button));
+    }
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        self.controls.input_up(InputCode::Mouse(button));
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.wheel += y;
+    }
+}
@@ -17,7 +17,7 @@ use crate::{
     boilerplates::{FrameInfo, Gamemode, RenderTargetStack},
     controls::InputSubscriber,
     modes::ModeLogo,
-    utils::draw::width_height_deficit,
+    utils::{draw::width_height_deficit, profile::Profile},
 };
 
 use macroquad::prelude::*;
@@ -29,7 +29,7 @@ const ASPECT_RATIO: f32 = WIDTH / HEIGHT;
 const UPDATES_PER_DRAW: u64 = 1;
 const UPDATE_DT: f32 = 1.0 / (30.0 * UPDATES_PER_DRAW as f32);
 
-/// The `macroquad::main` macro uses this.
+/// `main` opens the window with this.
 fn window_conf() -> Conf {
     Conf {
         window_title: if cfg!(debug_assertions) {
@@ -44,9 +44,16 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    gameloop().await;
+// This is what `#[macroquad::main]` would expand to,
+// plus somewhere to do things once the window is closed.
+fn main() {
+    macroquad::Window::from_config(window_conf(), gameloop());
+    // Anything changed since the last save would be lost otherwise.
+    // On the web this returns right away while the game keeps running in the page,
+    // and there's no hook for the page closing, so it relies on `Profile::tick`;
+    // at worst the last second of edits is lost.
+    #[cfg(not(target_arch = "wasm32"))]
+    Profile::flush();
 }
 
 /// Threaded version of main.
@@ -62,10 +69,17 @@ async fn gameloop() {
     let mut controls = InputSubscriber::new();
 
     let (draw_tx, draw_rx) = crossbeam::channel::bounded(0);
+    // Reloaded assets get sent over to the update thread
+    #[cfg(debug_assertions)]
+    let (reload_tx, reload_rx) = crossbeam::channel::unbounded::<(&'static Assets, Vec<_>)>();
+    #[cfg(debug_assertions)]
+    let mut watcher = assets::Watcher::new();
 
     // Drawing must happen on the main thread (thanks macroquad...)
     // so updating goes over here
     let _update_handle = thread::spawn(move || {
+        #[cfg(debug_assertions)]
+        let mut assets = assets;
         let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeLogo::new())];
         let mut frame_info = FrameInfo {
             dt: UPDATE_DT,
@@ -73,6 +87,14 @@ async fn gameloop() {
         };
 
         loop {
+            #[cfg(debug_assertions)]
+            if let Ok((new_assets, changed)) = reload_rx.try_recv() {
+                assets = new_assets;
+                for mode in mode_stack.iter_mut() {
+                    mode.on_assets_reloaded(assets, &changed);
+                }
+            }
+
             controls.update();
             // Update the current state.
             // To change state, return a non-None transition.
@@ -81,6 +103,7 @@ async fn gameloop() {
                 .unwrap()
                 .update(&controls, frame_info, assets);
             transition.apply(&mut mode_stack, &assets);
+            Profile::tick(frame_info.frames_ran);
 
             #[allow(clippy::modulo_one)]
             if frame_info.frames_ran % UPDATES_PER_DRAW == 0 {
@@ -94,6 +117,8 @@ async fn gameloop() {
     });
 
     // Draw loop
+    #[cfg(debug_assertions)]
+    let mut assets = assets;
     let mut frame_info = FrameInfo {
         dt: 0.0,
         frames_ran: 0,
@@ -101,6 +126,16 @@ async fn gameloop() {
     loop {
         frame_info.dt = macroquad::time::get_frame_time();
 
+        #[cfg(debug_assertions)]
+        {
+            let changed = watcher.poll();
+            if !changed.is_empty() {
+                // The old assets might still be in use by the update thread, so they stay leaked
+                assets = Box::leak(Box::new(assets.reload(&changed).await));
+                let _ = reload_tx.send((assets, changed));
+            }
+        }
+
         let drawer = match draw_rx.try_recv() {
             Ok(it) => it,
             Err(TryRecvError::Empty) => draw_rx.recv().unwrap(),
@@ -146,6 +181,8 @@ async fn gameloop() {
     let assets = Assets::init().await;
     let assets = Box::leak(Box::new(assets)) as &'static Assets;
     info!("Made assets");
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    let (mut assets, mut watcher) = (assets, assets::Watcher::new());
 
     let mut controls = InputSubscriber::new();
     let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeLogo::new())];
@@ -170,6 +207,17 @@ async fn gameloop() {
 
         frame_info.dt = UPDATE_DT;
 
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+        {
+            let changed = watcher.poll();
+            if !changed.is_empty() {
+                assets = Box::leak(Box::new(assets.reload(&changed).await));
+                for mode in mode_stack.iter_mut() {
+                    mode.on_assets_reloaded(assets, &changed);
+                }
+            }
+        }
+
         // Update the current state.
         // To change state, return a non-None transition.
         for _ in 0..UPDATES_PER_DRAW {
@@ -181,6 +229,7 @@ async fn gameloop() {
                 .update(&controls, frame_info, assets);
             transition.apply(&mut mode_stack, assets);
         }
+        Profile::tick(frame_info.frames_ran);
 
         frame_info.dt = macroquad::time::get_frame_time();
         // These divides and multiplies are required to get the camera in the center of the screen
@@ -0,0 +1,201 @@
+use cogs_gamedev::controls::InputHandler;
+
+use crate::{
+    assets::{packs_dir, Assets, LevelSet},
+    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, RenderTargetStack, Transition},
+    controls::{Control, InputSubscriber},
+    modes::ModeLevelSelect,
+    utils::{
+        draw::{self, draw_space, mouse_position_pixel},
+        profile::Profile,
+    },
+};
+
+/// Rows of text before the first pack
+const HEADER_ROWS: usize = 2;
+/// Packs on each page
+const PER_PAGE: usize = 10;
+/// Characters that fit across the textbox
+const CHARS_ACROSS: usize = 58;
+
+/// Screen for picking a level pack the player put in the packs folder.
+#[derive(Clone)]
+pub struct ModeCustomLevels {
+    /// One line per pack
+    packs: Vec<String>,
+    /// Which pack is highlighted
+    cursor: usize,
+    page: usize,
+    last_mouse: (f32, f32),
+}
+
+impl ModeCustomLevels {
+    pub fn new(assets: &Assets) -> Self {
+        Self {
+            packs: Self::get_pack_lines(assets),
+            cursor: 0,
+            page: 0,
+            last_mouse: mouse_position_pixel(),
+        }
+    }
+
+    fn get_pack_lines(assets: &Assets) -> Vec<String> {
+        let profile = Profile::get();
+        assets
+            .packs
+            .iter()
+            .map(|pack| {
+                let solved = pack
+                    .levels
+                    .iter()
+                    .filter(|level| {
+                        matches!(profile.solutions.get(&level.key()), Some(soln) if soln.metrics.is_some())
+                    })
+                    .count();
+                let problems = match pack.errors.len() {
+                    0 => String::new(),
+                    1 => " (1 PROBLEM)".to_owned(),
+                    n => format!(" ({} PROBLEMS)", n),
+                };
+                format!(
+                    "{:.<30}{}/{} SOLVED{}",
+                    pack.name.to_uppercase(),
+                    solved,
+                    pack.levels.len(),
+                    problems
+                )
+            })
+            .collect()
+    }
+
+    /// Get the pack the mouse is over, if it's over one.
+    fn hovered_pack(&self) -> Option<usize> {
+        let on_page = self
+            .packs
+            .len()
+            .saturating_sub(self.page * PER_PAGE)
+            .min(PER_PAGE);
+        draw::hovered_menu_row(HEADER_ROWS, on_page).map(|row| self.page * PER_PAGE + row)
+    }
+
+    fn page_count(&self) -> usize {
+        ((self.packs.len() + PER_PAGE - 1) / PER_PAGE).max(1)
+    }
+
+    /// Highlight the pack, flipping to its page.
+    fn select(&mut self, idx: usize) {
+        self.cursor = idx;
+        self.page = idx / PER_PAGE;
+    }
+
+    fn flip_page(&mut self, forwards: bool) {
+        let pages = self.page_count();
+        self.page = if forwards {
+            (self.page + 1) % pages
+        } else {
+            (self.page + pages - 1) % pages
+        };
+        self.cursor = self.page * PER_PAGE;
+    }
+
+    fn get_text(&self) -> String {
+        let mut lines = vec!["  CUSTOM LEVELS".to_owned(), String::new()];
+        if self.packs.is_empty() {
+            lines.push("  NO LEVEL PACKS YET".to_owned());
+        }
+        for (idx, pack) in self
+            .packs
+            .iter()
+            .enumerate()
+            .skip(self.page * PER_PAGE)
+            .take(PER_PAGE)
+        {
+            lines.push(format!(
+                "{} {}",
+                if idx == self.cursor { ">" } else { " " },
+                pack
+            ));
+        }
+        if self.page_count() > 1 {
+            lines.push(format!(
+                "  < PAGE {}/{} >",
+                self.page + 1,
+                self.page_count()
+            ));
+        }
+
+        lines.push(String::new());
+        match packs_dir() {
+            Some(dir) => {
+                lines.push("PUT LEVEL PACKS IN FOLDERS IN".to_owned());
+                // Paths can get long
+                let dir = dir.to_string_lossy().chars().collect::<Vec<_>>();
+                for chunk in dir.chunks(CHARS_ACROSS) {
+                    lines.push(chunk.iter().collect());
+                }
+                lines.push("EACH WITH A MANIFEST.JSON AND LEVEL FILES.".to_owned());
+            }
+            None => lines.push("LEVEL PACKS AREN'T SUPPORTED HERE".to_owned()),
+        }
+        lines.join("\n")
+    }
+}
+
+impl Gamemode for ModeCustomLevels {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        if controls.clicked_down(Control::Escape) {
+            return Transition::Pop;
+        }
+        if self.packs.is_empty() {
+            return Transition::None;
+        }
+
+        let mouse = mouse_position_pixel();
+        if mouse != self.last_mouse {
+            self.last_mouse = mouse;
+            if let Some(idx) = self.hovered_pack() {
+                self.cursor = idx;
+            }
+        }
+        if controls.clicked_down(Control::Up) {
+            self.select(self.cursor.checked_sub(1).unwrap_or(self.packs.len() - 1));
+        } else if controls.clicked_down(Control::Down) {
+            self.select((self.cursor + 1) % self.packs.len());
+        } else if controls.clicked_down(Control::Left) {
+            self.flip_page(false);
+        } else if controls.clicked_down(Control::Right) {
+            self.flip_page(true);
+        }
+
+        if controls.clicked_down(Control::Select) {
+            return Transition::Push(Box::new(ModeLevelSelect::new(
+                LevelSet::Pack(self.cursor),
+                assets,
+            )));
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        Box::new(self.clone())
+    }
+
+    fn on_resume(&mut self, assets: &Assets) {
+        self.packs = Self::get_pack_lines(assets);
+        // Packs might have gone away while we were gone
+        self.select(self.cursor.min(self.packs.len().saturating_sub(1)));
+    }
+}
+
+impl GamemodeDrawer for ModeCustomLevels {
+    fn draw(&self, assets: &Assets, frame_info: FrameInfo, render_targets: &mut RenderTargetStack) {
+        draw_space(assets);
+        draw::menu_box(&self.get_text(), assets);
+    }
+}
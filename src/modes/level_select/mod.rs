@@ -1,11 +1,14 @@
+use std::path::PathBuf;
+
 use cogs_gamedev::controls::InputHandler;
-use macroquad::prelude::info;
+use macroquad::prelude::{vec2, Camera2D, Rect};
 
 use crate::{
-    assets::{Assets, Level},
+    assets::{Assets, Level, LevelSet},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, RenderTargetStack, Transition},
-    controls::{Control, InputSubscriber},
-    modes::ModePlaying,
+    controls::{Control, InputCode, InputSubscriber},
+    modes::{ModeCustomLevels, ModeOptions, ModePlaying},
+    simulator::board::Board,
     utils::{
         draw::{self, draw_space, mouse_position_pixel},
         profile::Profile,
@@ -13,78 +16,217 @@ use crate::{
     HEIGHT, WIDTH,
 };
 
-const TEXTBOX_WIDTH: usize = 16;
-const TEXTBOX_HEIGHT: usize = 9;
+/// Cards across each page
+const COLUMNS: usize = 3;
+const ROWS: usize = 2;
+const PER_PAGE: usize = COLUMNS * ROWS;
+
+/// In tiles
+const CARD_WIDTH: usize = 6;
+const CARD_HEIGHT: usize = 5;
+const CARD_GAP: f32 = 8.0;
+
+const GRID_X: f32 =
+    (WIDTH - COLUMNS as f32 * CARD_WIDTH as f32 * 16.0 - (COLUMNS - 1) as f32 * CARD_GAP) / 2.0;
+const GRID_Y: f32 = 32.0;
 
-const CORNER_X: f32 = WIDTH / 2.0 - TEXTBOX_WIDTH as f32 * 16.0 / 2.0;
-const CORNER_Y: f32 = HEIGHT / 2.0 - TEXTBOX_HEIGHT as f32 * 16.0 / 2.0;
+/// Where the page arrows and options go
+const BAR_Y: f32 = HEIGHT - 22.0;
 
-const TEXT_OFFSET_X: f32 = 8.0;
-const TEXT_OFFSET_Y: f32 = 12.0;
+/// Padding inside the cards
+const CARD_PADDING: f32 = 6.0;
+/// Height of the thumbnail; the text goes under it
+const THUMBNAIL_HEIGHT: f32 = 48.0;
 
 #[derive(Clone)]
 pub struct ModeLevelSelect {
-    text: String,
+    level_set: LevelSet,
+    /// Shown at the top when the level has no chapter
+    title: String,
+    cards: Vec<Card>,
     advanced: bool,
+
+    /// Which entry is highlighted. After all the levels comes the options,
+    /// and then the custom levels if these are the built-in ones.
+    selected: usize,
+    page: usize,
+    last_mouse: (f32, f32),
+    /// Shown at the bottom, like when trying to play a locked level
+    message: String,
+}
+
+/// Everything shown about one level.
+#[derive(Clone)]
+struct Card {
+    name: String,
+    chapter: String,
+    description: String,
+    /// Who made it, and how hard it is
+    byline: String,
+    /// The level, with the player's cables on it if they have any
+    board: Board,
+    /// Metrics, if it's solved
+    solved: Option<String>,
+    /// Par metrics, if there are any
+    par: Option<String>,
+    /// How many more levels have to be solved to play this, if it's locked
+    locked: Option<usize>,
+}
+
+/// Things the mouse can be over
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Hovered {
+    /// A level, or the options or custom levels
+    Entry(usize),
+    PrevPage,
+    NextPage,
 }
 
 impl ModeLevelSelect {
-    pub fn new(levels: &[Level]) -> Self {
+    pub fn new(level_set: LevelSet, assets: &Assets) -> Self {
         Self {
-            text: Self::get_text(levels, false),
+            level_set,
+            title: match level_set {
+                LevelSet::BuiltIn => "LEVEL SELECT".to_owned(),
+                LevelSet::Pack(idx) => assets.packs[idx].name.to_uppercase(),
+            },
+            cards: Self::get_cards(level_set.levels(assets), false),
             advanced: false,
+            selected: 0,
+            page: 0,
+            last_mouse: mouse_position_pixel(),
+            message: String::new(),
         }
     }
 
-    fn get_text(levels: &[Level], advanced: bool) -> String {
+    fn get_cards(levels: &[Level], advanced: bool) -> Vec<Card> {
         let profile = Profile::get();
-        let lines = (0..levels.len())
-            .map(|idx| {
-                let level = &levels[idx];
-                let soln = profile.solutions.get(&level.filename);
 
-                let name = if advanced {
-                    &level.filename
-                } else {
-                    &level.name
-                };
-                if let Some(soln) = soln {
-                    if let Some(metrics) = &soln.metrics {
-                        format!(
-                            "- {} ({} CYCLES, {} MIN CYCLES, {} XOVERS)",
-                            name, metrics.total_cycles, metrics.min_cycles, metrics.crossovers
-                        )
+        levels
+            .iter()
+            .map(|level| {
+                let soln = profile.solutions.get(&level.key());
+
+                let mut board = level.starting_board.clone();
+                if let Some(soln) = soln.filter(|soln| soln.fits(&level.starting_board)) {
+                    board.cables = soln.cables.clone();
+                    board.connectors = soln.connectors.clone();
+                }
+
+                let solved = soln.and_then(|soln| soln.metrics.as_ref()).map(|metrics| {
+                    format!(
+                        "{} CYC {} MIN {} X",
+                        metrics.total_cycles, metrics.min_cycles, metrics.crossovers
+                    )
+                });
+
+                let mut byline = Vec::new();
+                if !level.author.is_empty() {
+                    byline.push(format!("BY {}", level.author));
+                }
+                if level.difficulty > 0 {
+                    byline.push(format!("DIFFICULTY {}/5", level.difficulty));
+                }
+
+                Card {
+                    name: if advanced {
+                        level.filename.clone()
                     } else {
-                        format!("- {}", name)
-                    }
-                } else {
-                    format!("- {}", name)
+                        level.name.clone()
+                    },
+                    chapter: level.chapter.clone(),
+                    description: level.description.clone(),
+                    byline: byline.join("  "),
+                    board,
+                    solved,
+                    par: level
+                        .par
+                        .as_ref()
+                        .map(|par| format!("PAR {} CYC {} X", par.total_cycles, par.crossovers)),
+                    locked: solves_needed(levels, level, &profile),
                 }
             })
-            .collect::<Vec<_>>();
-        format!("  LEVEL SELECT\n\n{}", lines.join("\n"))
+            .collect()
+    }
+
+    /// How many things can be selected, counting the ones in the bottom bar.
+    fn entry_count(&self) -> usize {
+        self.cards.len()
+            + match self.level_set {
+                LevelSet::BuiltIn => 2,
+                LevelSet::Pack(_) => 1,
+            }
     }
 
-    fn get_hovered_char(&self) -> (Option<usize>, Option<usize>) {
+    fn page_count(&self) -> usize {
+        ((self.cards.len() + PER_PAGE - 1) / PER_PAGE).max(1)
+    }
+
+    /// Get the top-left corner of the card in this slot on the page.
+    fn card_corner(slot: usize) -> (f32, f32) {
+        let col = slot % COLUMNS;
+        let row = slot / COLUMNS;
+        (
+            GRID_X + col as f32 * (CARD_WIDTH as f32 * 16.0 + CARD_GAP),
+            GRID_Y + row as f32 * (CARD_HEIGHT as f32 * 16.0 + CARD_GAP),
+        )
+    }
+
+    /// Rectangles of the bottom bar: previous page, next page, custom levels and options.
+    fn bar_rects() -> (Rect, Rect, Rect, Rect) {
+        (
+            Rect::new(GRID_X, BAR_Y, 16.0, 12.0),
+            Rect::new(GRID_X + 96.0, BAR_Y, 16.0, 12.0),
+            Rect::new(WIDTH - GRID_X - 88.0, BAR_Y, 40.0, 12.0),
+            Rect::new(WIDTH - GRID_X - 40.0, BAR_Y, 40.0, 12.0),
+        )
+    }
+
+    /// Get what the mouse is over, if anything.
+    fn get_hovered(&self) -> Option<Hovered> {
         let (mx, my) = mouse_position_pixel();
+        let mouse = vec2(mx, my);
 
-        let offset = my - CORNER_Y - TEXT_OFFSET_Y;
-        let row = offset / 6.0;
-        let row = if row > 0.0 && row < 16.0 * 9.0 {
-            Some(row as usize)
-        } else {
-            None
-        };
+        let (prev, next, custom, options) = Self::bar_rects();
+        if prev.contains(mouse) {
+            return Some(Hovered::PrevPage);
+        } else if next.contains(mouse) {
+            return Some(Hovered::NextPage);
+        } else if options.contains(mouse) {
+            return Some(Hovered::Entry(self.cards.len()));
+        } else if custom.contains(mouse) && self.level_set == LevelSet::BuiltIn {
+            return Some(Hovered::Entry(self.cards.len() + 1));
+        }
+
+        (0..PER_PAGE).find_map(|slot| {
+            let idx = self.page * PER_PAGE + slot;
+            let (x, y) = Self::card_corner(slot);
+            let rect = Rect::new(x, y, CARD_WIDTH as f32 * 16.0, CARD_HEIGHT as f32 * 16.0);
+            if idx < self.cards.len() && rect.contains(mouse) {
+                Some(Hovered::Entry(idx))
+            } else {
+                None
+            }
+        })
+    }
 
-        let offset = mx - CORNER_X - TEXT_OFFSET_X;
-        let col = offset / 4.0;
-        let col = if col > 0.0 && col < 16.0 * 13.0 {
-            Some(col as usize)
+    /// Highlight the entry, flipping to its page.
+    fn select(&mut self, idx: usize) {
+        self.selected = idx;
+        // The bottom bar is on every page
+        if idx < self.cards.len() {
+            self.page = idx / PER_PAGE;
+        }
+    }
+
+    fn flip_page(&mut self, forwards: bool) {
+        let pages = self.page_count();
+        self.page = if forwards {
+            (self.page + 1) % pages
         } else {
-            None
+            (self.page + pages - 1) % pages
         };
-
-        (row, col)
+        self.selected = self.page * PER_PAGE;
     }
 }
 
@@ -95,24 +237,69 @@ impl Gamemode for ModeLevelSelect {
         frame_info: FrameInfo,
         assets: &Assets,
     ) -> Transition {
+        if controls.clicked_down(Control::Escape) && self.level_set != LevelSet::BuiltIn {
+            return Transition::Pop;
+        }
+
         let advanced = controls.pressed(Control::Ctrl);
         if advanced != self.advanced {
             self.advanced = advanced;
-            self.text = Self::get_text(&assets.levels, self.advanced);
+            self.cards = Self::get_cards(self.level_set.levels(assets), self.advanced);
+        }
+
+        let entries = self.entry_count();
+        let mouse = mouse_position_pixel();
+        if mouse != self.last_mouse {
+            self.last_mouse = mouse;
+            if let Some(Hovered::Entry(hovered)) = self.get_hovered() {
+                self.selected = hovered;
+            }
+        }
+
+        if controls.clicked_down(Control::Left) {
+            self.select((self.selected + entries - 1) % entries);
+        } else if controls.clicked_down(Control::Right) {
+            self.select((self.selected + 1) % entries);
+        } else if controls.clicked_down(Control::Up) {
+            self.select(self.selected.saturating_sub(COLUMNS));
+        } else if controls.clicked_down(Control::Down) {
+            self.select((self.selected + COLUMNS).min(entries - 1));
         }
 
         if controls.clicked_down(Control::Select) {
-            let (row, col) = self.get_hovered_char();
-            info!("{:?}, {:?}", row, col);
+            // Clicking goes by where the mouse is; everything else by the highlight
+            let chosen = if matches!(controls.last_input(), Some(InputCode::Mouse(_))) {
+                self.get_hovered()
+            } else {
+                Some(Hovered::Entry(self.selected))
+            };
 
-            if let (Some(row), Some(col)) = (row, col) {
-                if row >= 2 {
-                    let level_idx = row - 2;
-                    if let Some(new_level) = assets.levels.get(level_idx) {
+            match chosen {
+                Some(Hovered::PrevPage) => self.flip_page(false),
+                Some(Hovered::NextPage) => self.flip_page(true),
+                Some(Hovered::Entry(idx)) if idx == self.cards.len() => {
+                    return Transition::Push(Box::new(ModeOptions::new()));
+                }
+                Some(Hovered::Entry(idx)) if idx > self.cards.len() => {
+                    return Transition::Push(Box::new(ModeCustomLevels::new(assets)));
+                }
+                Some(Hovered::Entry(idx)) => {
+                    if let Some(more) = self.cards[idx].locked {
+                        self.message = format!(
+                            "SOLVE {} MORE LEVEL{} TO UNLOCK THAT",
+                            more,
+                            if more == 1 { "" } else { "S" }
+                        );
+                    } else {
                         // Maybe load a solution?
-                        return Transition::Push(Box::new(ModePlaying::new(new_level, level_idx)));
+                        return Transition::Push(Box::new(ModePlaying::new(
+                            &self.level_set.levels(assets)[idx],
+                            idx,
+                            self.level_set,
+                        )));
                     }
                 }
+                None => {}
             }
         }
 
@@ -124,7 +311,15 @@ impl Gamemode for ModeLevelSelect {
     }
 
     fn on_resume(&mut self, assets: &Assets) {
-        self.text = Self::get_text(&assets.levels, false);
+        self.cards = Self::get_cards(self.level_set.levels(assets), false);
+        self.message.clear();
+    }
+
+    fn on_assets_reloaded(&mut self, assets: &Assets, _changed: &[PathBuf]) {
+        self.cards = Self::get_cards(self.level_set.levels(assets), self.advanced);
+        // There might be fewer levels now
+        self.select(self.selected.min(self.cards.len()));
+        self.page = self.page.min(self.page_count() - 1);
     }
 }
 
@@ -134,30 +329,245 @@ impl GamemodeDrawer for ModeLevelSelect {
 
         draw_space(assets);
 
+        let text_color = draw::hexcolor(0xff5277_dd);
+        let on_page = (self.page * PER_PAGE..(self.page + 1) * PER_PAGE)
+            .filter(|idx| *idx < self.cards.len())
+            .enumerate()
+            .collect::<Vec<_>>();
+
         gl_use_material(assets.shaders.hologram);
         assets
             .shaders
             .hologram
             .set_uniform("time", macroquad::time::get_time() as f32);
+        for (slot, _) in on_page.iter() {
+            let (x, y) = Self::card_corner(*slot);
+            draw::patch9(
+                16.0,
+                x,
+                y,
+                CARD_WIDTH,
+                CARD_HEIGHT,
+                assets.textures.hologram_9patch,
+            );
+        }
+        gl_use_default_material();
+
+        for (slot, idx) in on_page {
+            let card = &self.cards[idx];
+            let (x, y) = Self::card_corner(slot);
+            let inner_width = CARD_WIDTH as f32 * 16.0 - CARD_PADDING * 2.0;
+            let text_y = y + CARD_PADDING + THUMBNAIL_HEIGHT + 4.0;
+            // leave a space for the checkmark
+            let chars_across = (inner_width / 4.0) as usize - 2;
+
+            if let Some(more) = card.locked {
+                draw::pixel_text(
+                    format!("LOCKED\n\nSOLVE {} MORE", more),
+                    x + CARD_PADDING,
+                    y + CARD_PADDING + 12.0,
+                    None,
+                    text_color,
+                    assets,
+                );
+            } else {
+                draw_thumbnail(
+                    &card.board,
+                    Rect::new(
+                        x + CARD_PADDING,
+                        y + CARD_PADDING,
+                        inner_width,
+                        THUMBNAIL_HEIGHT,
+                    ),
+                    assets,
+                    render_targets,
+                );
+            }
+
+            let name = card.name.chars().take(chars_across).collect::<String>();
+            let text = match (&card.solved, &card.par) {
+                (Some(metrics), _) => format!("{}\n{}", name, metrics),
+                (None, Some(par)) => format!("{}\n{}", name, par),
+                (None, None) => name,
+            };
+            draw::pixel_text(text, x + CARD_PADDING, text_y, None, text_color, assets);
 
-        draw::patch9(
-            16.0,
-            CORNER_X,
-            CORNER_Y,
-            TEXTBOX_WIDTH,
-            TEXTBOX_HEIGHT,
-            assets.textures.hologram_9patch,
+            if card.solved.is_some() {
+                // checkmark in the corner, sea green
+                let color = draw::hexcolor(0x92e8c0_ff);
+                let cx = x + CARD_WIDTH as f32 * 16.0 - CARD_PADDING - 7.0;
+                let cy = text_y + 1.0;
+                draw_line(cx, cy + 2.0, cx + 2.0, cy + 4.0, 1.5, color);
+                draw_line(cx + 2.0, cy + 4.0, cx + 6.0, cy - 1.0, 1.5, color);
+            }
+
+            if idx == self.selected {
+                // yellow
+                draw_rectangle_lines(
+                    x - 2.0,
+                    y - 2.0,
+                    CARD_WIDTH as f32 * 16.0 + 4.0,
+                    CARD_HEIGHT as f32 * 16.0 + 4.0,
+                    2.0,
+                    draw::hexcolor(0xffee83_dd),
+                );
+            }
+        }
+
+        // Say what chapter we're looking at
+        let title = match self.cards.get(self.selected) {
+            Some(card) if !card.chapter.is_empty() => card.chapter.clone(),
+            _ => self.title.clone(),
+        };
+        draw::pixel_text(
+            &title,
+            WIDTH / 2.0 - title.len() as f32 * 4.0 / 2.0,
+            12.0,
+            None,
+            text_color,
+            assets,
         );
 
+        let (prev, next, custom, options) = Self::bar_rects();
+        draw::pixel_text("<", prev.x + 6.0, prev.y + 3.0, None, text_color, assets);
         draw::pixel_text(
-            &self.text,
-            CORNER_X + TEXT_OFFSET_X,
-            CORNER_Y + TEXT_OFFSET_Y,
+            format!("PAGE {}/{}", self.page + 1, self.page_count()),
+            prev.x + 28.0,
+            prev.y + 3.0,
             None,
-            draw::hexcolor(0xff5277_dd),
+            text_color,
             assets,
         );
+        draw::pixel_text(">", next.x + 6.0, next.y + 3.0, None, text_color, assets);
+        draw::pixel_text(
+            format!(
+                "{}OPTIONS",
+                if self.selected == self.cards.len() {
+                    ">"
+                } else {
+                    " "
+                }
+            ),
+            options.x + 4.0,
+            options.y + 3.0,
+            None,
+            text_color,
+            assets,
+        );
+        if self.level_set == LevelSet::BuiltIn {
+            draw::pixel_text(
+                format!(
+                    "{}CUSTOM",
+                    if self.selected == self.cards.len() + 1 {
+                        ">"
+                    } else {
+                        " "
+                    }
+                ),
+                custom.x + 4.0,
+                custom.y + 3.0,
+                None,
+                text_color,
+                assets,
+            );
+        }
 
-        gl_use_default_material();
+        let footer = if !self.message.is_empty() {
+            Some(&self.message)
+        } else {
+            self.cards.get(self.selected).map(|card| &card.byline)
+        };
+        if let Some(footer) = footer {
+            draw::pixel_text(
+                footer,
+                WIDTH / 2.0 - footer.len() as f32 * 4.0 / 2.0,
+                BAR_Y - 8.0,
+                None,
+                text_color,
+                assets,
+            );
+        }
+
+        let errors = self.level_set.errors(assets);
+        if !errors.is_empty() {
+            let text = if errors.len() == 1 {
+                "! 1 PROBLEM LOADING LEVELS".to_owned()
+            } else {
+                format!("! {} PROBLEMS LOADING LEVELS", errors.len())
+            };
+            let width = text.len() as f32 * 4.0;
+            let rect = Rect::new(WIDTH / 2.0 - width / 2.0, 20.0, width, 6.0);
+            // yellow
+            draw::pixel_text(
+                &text,
+                rect.x,
+                rect.y,
+                None,
+                draw::hexcolor(0xffee83_ff),
+                assets,
+            );
+
+            let (mx, my) = mouse_position_pixel();
+            if rect.contains(vec2(mx, my)) {
+                let details = errors
+                    .iter()
+                    .map(|err| err.describe(74))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                draw::tooltip(&details, 0.0, rect.bottom() + 2.0, assets);
+                // Don't cover it up with a briefing
+                return;
+            }
+        }
+
+        // Show the briefing for whatever level the mouse is over
+        if let Some(Hovered::Entry(idx)) = self.get_hovered() {
+            if let Some(card) = self.cards.get(idx) {
+                if card.locked.is_none() && !card.description.is_empty() {
+                    let (mx, my) = mouse_position_pixel();
+                    draw::tooltip(&card.description, mx + 8.0, my + 8.0, assets);
+                }
+            }
+        }
     }
 }
+
+/// How many more of the levels have to be solved before this one can be played,
+/// or `None` if it's unlocked.
+pub fn solves_needed(levels: &[Level], level: &Level, profile: &Profile) -> Option<usize> {
+    let solved_count = levels
+        .iter()
+        .filter(|level| {
+            matches!(profile.solutions.get(&level.key()), Some(soln) if soln.metrics.is_some())
+        })
+        .count();
+    level
+        .solves_to_unlock
+        .checked_sub(solved_count)
+        .filter(|&more| more > 0)
+}
+
+/// Draw the board shrunk down to fit in the rectangle.
+fn draw_thumbnail(
+    board: &Board,
+    rect: Rect,
+    assets: &Assets,
+    render_targets: &mut RenderTargetStack,
+) {
+    // The board draws itself centered on the canvas;
+    // the connectors stick out one tile on each side.
+    let scale = (rect.w / ((board.width + 2) as f32 * 16.0))
+        .min(rect.h / ((board.height + 2) as f32 * 16.0));
+    let canvas_center = vec2(WIDTH / 2.0, HEIGHT / 2.0);
+    let rect_center = vec2(rect.x + rect.w / 2.0, rect.y + rect.h / 2.0);
+
+    let top = *render_targets.get_stack().last().unwrap();
+    render_targets.push(Camera2D {
+        zoom: top.zoom * scale,
+        // Put the canvas center at the rect center
+        target: canvas_center - (rect_center - canvas_center) / scale,
+        ..top
+    });
+    board.draw(assets);
+    render_targets.pop();
+}
@@ -1,5 +1,5 @@
 use crate::{
-    assets::Assets,
+    assets::{Assets, LevelSet},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, RenderTargetStack, Transition},
     controls::{Control, InputSubscriber},
     utils::draw::{self, hexcolor},
@@ -96,7 +96,7 @@ impl Gamemode for ModeLogo {
             || controls.clicked_down(Control::Select)
         {
             macroquad::audio::stop_sound(assets.sounds.title_jingle);
-            Transition::Swap(Box::new(ModeLevelSelect::new(&assets.levels)))
+            Transition::Swap(Box::new(ModeLevelSelect::new(LevelSet::BuiltIn, assets)))
         } else {
             Transition::None
         }
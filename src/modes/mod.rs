@@ -6,3 +6,7 @@ mod level_select;
 pub use level_select::ModeLevelSelect;
 mod ending;
 pub use ending::ModeEnding;
+mod options;
+pub use options::ModeOptions;
+mod custom_levels;
+pub use custom_levels::ModeCustomLevels;
@@ -0,0 +1,248 @@
+use cogs_gamedev::controls::InputHandler;
+
+use crate::{
+    assets::Assets,
+    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, RenderTargetStack, Transition},
+    controls::{Control, InputCode, InputSubscriber},
+    utils::{
+        draw::{self, draw_space, mouse_position_pixel},
+        profile::Profile,
+    },
+};
+
+use std::collections::HashMap;
+
+/// Rows of text before the first control
+const HEADER_ROWS: usize = 2;
+/// The row after all the controls resets them
+const RESET_ROW: usize = Control::ALL.len();
+
+/// Screen for rebinding the controls.
+#[derive(Clone)]
+pub struct ModeOptions {
+    keymap: HashMap<InputCode, Control>,
+    /// Which row is highlighted
+    cursor: usize,
+    state: RebindState,
+    /// Status line shown at the bottom
+    message: String,
+
+    last_mouse: (f32, f32),
+}
+
+#[derive(Clone)]
+enum RebindState {
+    /// Just looking around
+    Browsing,
+    /// Waiting for the player to press something to bind to this
+    Waiting(Control),
+    /// The input is already bound to something else;
+    /// press it again to steal it.
+    Conflict { control: Control, input: InputCode },
+}
+
+impl ModeOptions {
+    pub fn new() -> Self {
+        Self {
+            keymap: Profile::get().keymap.clone(),
+            cursor: 0,
+            state: RebindState::Browsing,
+            message: String::new(),
+            last_mouse: mouse_position_pixel(),
+        }
+    }
+
+    /// Bind the input to the control, replacing whatever it was bound to before.
+    ///
+    /// Refuses if that would leave you unable to select or go back.
+    fn rebind(&mut self, control: Control, input: InputCode) {
+        let new_keymap = rebound(&self.keymap, control, input);
+
+        if let Some(stranded) = [Control::Select, Control::Escape]
+            .iter()
+            .find(|needed| !new_keymap.values().any(|bound| bound == *needed))
+        {
+            self.message = format!("CAN'T; {} WOULD HAVE NOTHING BOUND", stranded.name());
+            return;
+        }
+
+        self.message = format!("BOUND {} TO {}", input.name(), control.name());
+        self.keymap = new_keymap;
+        self.save();
+    }
+
+    fn save(&self) {
+        let mut profile = Profile::get();
+        profile.keymap = self.keymap.clone();
+        drop(profile);
+        Profile::flush();
+        InputSubscriber::mark_keymap_dirty();
+    }
+
+    /// Say what's bound to going back, which cancels rebinding.
+    fn back_inputs(&self) -> String {
+        let mut inputs = self
+            .keymap
+            .iter()
+            .filter(|(_, bound)| **bound == Control::Escape)
+            .map(|(input, _)| input.name())
+            .collect::<Vec<_>>();
+        inputs.sort();
+        inputs.join(" OR ")
+    }
+
+    fn get_text(&self) -> String {
+        let mut lines = vec!["  OPTIONS - CONTROLS".to_owned(), String::new()];
+        for (idx, control) in Control::ALL.iter().enumerate() {
+            let mut inputs = self
+                .keymap
+                .iter()
+                .filter(|(_, bound)| *bound == control)
+                .map(|(input, _)| input.name())
+                .collect::<Vec<_>>();
+            inputs.sort();
+            let bindings = match &self.state {
+                RebindState::Waiting(waiting) if waiting == control => {
+                    "PRESS SOMETHING...".to_owned()
+                }
+                _ if inputs.is_empty() => "(NOTHING)".to_owned(),
+                _ => inputs.join(", "),
+            };
+            lines.push(format!(
+                "{} {:.<14}{}",
+                if idx == self.cursor { ">" } else { " " },
+                control.name(),
+                bindings
+            ));
+        }
+        lines.push(format!(
+            "{} RESET TO DEFAULTS",
+            if self.cursor == RESET_ROW { ">" } else { " " }
+        ));
+        lines.push(String::new());
+        lines.push(self.message.clone());
+        lines.join("\n")
+    }
+}
+
+/// Bind the input to the control in the keymap.
+/// This replaces what the control was bound to on the same device,
+/// so rebinding a key leaves the mouse and gamepad buttons alone.
+fn rebound(
+    keymap: &HashMap<InputCode, Control>,
+    control: Control,
+    input: InputCode,
+) -> HashMap<InputCode, Control> {
+    let mut new_keymap = keymap.clone();
+    new_keymap.retain(|bound_input, bound| *bound != control || !bound_input.same_device(&input));
+    new_keymap.insert(input, control);
+    new_keymap
+}
+
+impl Gamemode for ModeOptions {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &Assets,
+    ) -> Transition {
+        match self.state.clone() {
+            RebindState::Browsing => {
+                if controls.clicked_down(Control::Escape) {
+                    return Transition::Pop;
+                }
+
+                let mouse = mouse_position_pixel();
+                if mouse != self.last_mouse {
+                    self.last_mouse = mouse;
+                    if let Some(row) = draw::hovered_menu_row(HEADER_ROWS, RESET_ROW + 1) {
+                        self.cursor = row;
+                    }
+                }
+                if controls.clicked_down(Control::Up) {
+                    self.cursor = self.cursor.checked_sub(1).unwrap_or(RESET_ROW);
+                } else if controls.clicked_down(Control::Down) {
+                    self.cursor = (self.cursor + 1) % (RESET_ROW + 1);
+                }
+
+                if controls.clicked_down(Control::Select) {
+                    if self.cursor == RESET_ROW {
+                        self.keymap = InputSubscriber::default_controls();
+                        self.message = "RESET TO DEFAULTS".to_owned();
+                        self.save();
+                    } else {
+                        self.state = RebindState::Waiting(Control::ALL[self.cursor]);
+                        self.message = format!("PRESS {} TO CANCEL", self.back_inputs());
+                    }
+                }
+            }
+            RebindState::Waiting(control) => {
+                if let Some(input) = controls.last_input() {
+                    self.state = RebindState::Browsing;
+                    if self.keymap.get(&input) == Some(&Control::Escape) {
+                        self.message = String::new();
+                    } else {
+                        match self.keymap.get(&input) {
+                            Some(other) if *other != control => {
+                                self.message = format!(
+                                    "{} IS USED FOR {}; PRESS AGAIN TO MOVE IT",
+                                    input.name(),
+                                    other.name()
+                                );
+                                self.state = RebindState::Conflict { control, input };
+                            }
+                            _ => self.rebind(control, input),
+                        }
+                    }
+                }
+            }
+            RebindState::Conflict { control, input } => {
+                if let Some(pressed) = controls.last_input() {
+                    self.state = RebindState::Browsing;
+                    if pressed == input {
+                        self.rebind(control, input);
+                    } else {
+                        self.message = String::new();
+                    }
+                }
+            }
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeOptions {
+    fn draw(&self, assets: &Assets, frame_info: FrameInfo, render_targets: &mut RenderTargetStack) {
+        draw_space(assets);
+        draw::menu_box(&self.get_text(), assets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::GamepadButton;
+    use macroquad::prelude::{KeyCode, MouseButton};
+
+    #[test]
+    fn rebinding_a_key_keeps_the_other_devices() {
+        let keymap = InputSubscriber::default_controls();
+        let keymap = rebound(&keymap, Control::Select, InputCode::Key(KeyCode::Q));
+
+        let select = keymap
+            .iter()
+            .filter(|(_, bound)| **bound == Control::Select)
+            .map(|(input, _)| *input)
+            .collect::<Vec<_>>();
+        assert!(select.contains(&InputCode::Key(KeyCode::Q)));
+        assert!(select.contains(&InputCode::Mouse(MouseButton::Left)));
+        assert!(select.contains(&InputCode::Gamepad(GamepadButton::South)));
+        // The key it replaced is gone
+        assert!(!keymap.contains_key(&InputCode::Key(KeyCode::Enter)));
+    }
+}
@@ -4,9 +4,17 @@ use crate::{
     assets::Assets,
     boilerplates::{FrameInfo, GamemodeDrawer, RenderTargetStack},
     modes::playing::draw_space,
-    simulator::board::Board,
-    utils::draw::{self, hexcolor},
-    WIDTH,
+    simulator::{
+        board::Board,
+        lint::Diagnostic,
+        routes::{Route, RouteEnd},
+        transport::Cable,
+    },
+    utils::{
+        draw::{self, hexcolor},
+        view::View,
+    },
+    HEIGHT, WIDTH,
 };
 
 use super::ModePlaying;
@@ -16,8 +24,18 @@ pub(super) struct Drawer {
     board: Board,
     /// Hover position of the cursor
     cursor: ICoord,
+    view: View,
     /// If this is `true` the player is selecting at the cursor
     selecting: bool,
+    /// Corners of the rectangle picked out for copying
+    region: Option<(ICoord, ICoord)>,
+    /// Cables waiting to be pasted, and whether they can be pasted there
+    paste_ghost: Option<(Vec<(ICoord, Cable)>, bool)>,
+    /// Where each source's cables lead right now
+    routes: Vec<Route>,
+    /// Warnings about the board. Empty while dragging, because a half-laid
+    /// cable always has a loose end.
+    diagnostics: Vec<Diagnostic>,
 
     start_time: f64,
 
@@ -26,21 +44,24 @@ pub(super) struct Drawer {
 
 impl Drawer {
     pub fn new(mode: &ModePlaying) -> Self {
-        let cables = if let Some(sel) = &mode.selection {
-            sel.cables.clone()
-        } else {
-            mode.board.cables.clone()
-        };
-        let board = Board {
-            cables,
-            left: mode.board.left.clone(),
-            right: mode.board.right.clone(),
-            width: mode.board.width,
-        };
         Self {
-            board,
+            board: mode.shown_board(),
+            routes: mode.routes.clone(),
+            diagnostics: mode.diagnostics.clone(),
             cursor: mode.cursor,
+            view: mode.view,
             selecting: mode.selection.is_some(),
+            region: mode.region.as_ref().map(|region| region.corners()),
+            paste_ghost: match (&mode.clipboard, mode.pasting) {
+                (Some(clipboard), true) => Some((
+                    clipboard
+                        .placed_at(mode.cursor)
+                        .map(|(pos, cable)| (pos, cable.clone()))
+                        .collect(),
+                    clipboard.can_paste(mode.cursor, &mode.board),
+                )),
+                _ => None,
+            },
             start_time: mode.start_time,
             level_name: mode.level_name.clone(),
         }
@@ -53,7 +74,10 @@ impl GamemodeDrawer for Drawer {
 
         draw_space(assets);
 
+        self.view.push_camera(render_targets);
         self.board.draw(assets);
+        self.draw_routes();
+        self.draw_diagnostics(assets);
 
         let dt = macroquad::time::get_time() - self.start_time;
         let mut cursor_color = if self.selecting {
@@ -67,6 +91,67 @@ impl GamemodeDrawer for Drawer {
         let (cx, cy) = self.board.coord_to_px(self.cursor);
         draw_rectangle(cx, cy, 16.0, 16.0, cursor_color);
 
+        if let Some((min, max)) = self.region {
+            let (x1, y1) = self.board.coord_to_px(min);
+            let (x2, y2) = self.board.coord_to_px(max);
+            // sea green
+            draw_rectangle_lines(
+                x1,
+                y1,
+                x2 - x1 + 16.0,
+                y2 - y1 + 16.0,
+                2.0,
+                hexcolor(0x92e8c0_ff),
+            );
+        }
+
+        if let Some((cables, ok)) = &self.paste_ghost {
+            let color = if *ok {
+                Color::new(1.0, 1.0, 1.0, 0.6)
+            } else {
+                // red
+                hexcolor(0xff5277_99)
+            };
+            for (pos, cable) in cables.iter() {
+                let (cx, cy) = self.board.coord_to_px(*pos);
+                let (sxy1, sxy2) = cable.get_slices();
+                for (sx, sy) in std::iter::once(sxy1).chain(sxy2) {
+                    draw_texture_ex(
+                        assets.textures.cable_atlas,
+                        cx,
+                        cy,
+                        color,
+                        DrawTextureParams {
+                            source: Some(Rect::new(sx, sy, 16.0, 16.0)),
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+        }
+
+        render_targets.pop();
+
+        if !self.diagnostics.is_empty() {
+            let text = if self.diagnostics.len() == 1 {
+                "1 WARNING".to_owned()
+            } else {
+                format!("{} WARNINGS", self.diagnostics.len())
+            };
+            let text_x = WIDTH / 2.0 - text.len() as f32 * 4.0 / 2.0;
+            // yellow
+            draw::pixel_text(
+                text,
+                text_x,
+                HEIGHT - 18.0,
+                None,
+                hexcolor(0xffee83_ff),
+                assets,
+            );
+        }
+
+        let hovered_port = self.board.get_port(self.cursor);
+
         let text_x = WIDTH / 2.0 - self.level_name.len() as f32 * 4.0 / 2.0;
         draw::pixel_text(
             &self.level_name,
@@ -76,5 +161,101 @@ impl GamemodeDrawer for Drawer {
             hexcolor(0xff5277_ff),
             assets,
         );
+
+        if !self.selecting {
+            let lines = hovered_port
+                .map(|(port, _)| port.describe())
+                .into_iter()
+                .chain(
+                    self.diagnostics
+                        .iter()
+                        .filter(|diag| diag.pos == self.cursor)
+                        .map(|diag| format!("WARNING: {}", diag.problem.describe())),
+                )
+                .collect::<Vec<_>>();
+            if !lines.is_empty() {
+                let (tx, ty) = self.view.to_canvas((cx + 16.0, cy + 16.0));
+                draw::tooltip(&lines.join("\n"), tx, ty, assets);
+            }
+        }
+    }
+}
+
+impl Drawer {
+    /// Mark each tile with warnings.
+    fn draw_diagnostics(&self, assets: &Assets) {
+        // yellow
+        let color = hexcolor(0xffee83_ff);
+        let mut marked = Vec::new();
+        for diag in self.diagnostics.iter() {
+            if !marked.contains(&diag.pos) {
+                marked.push(diag.pos);
+                let (cx, cy) = self.board.coord_to_px(diag.pos);
+                draw::pixel_text("!", cx + 12.0, cy + 1.0, None, color, assets);
+            }
+        }
+    }
+
+    /// Draw a line along each route in its resource's color,
+    /// and an X where it goes wrong.
+    fn draw_routes(&self) {
+        use macroquad::prelude::*;
+
+        let center = |pos: ICoord| {
+            let (x, y) = self.board.coord_to_px(pos);
+            vec2(x + 8.0, y + 8.0)
+        };
+
+        for route in self.routes.iter() {
+            let mut color = route.resource.color();
+            color.a = 0.7;
+
+            let last = route
+                .tiles
+                .last()
+                .map(|(pos, _)| *pos)
+                .unwrap_or(route.source);
+            let end = match route.end {
+                // Go all the way into the port
+                RouteEnd::Delivered(pos) | RouteEnd::WrongSink(pos) => center(pos),
+                // Stop at the edge of the last tile
+                RouteEnd::Dangling(pos) | RouteEnd::Blocked(pos) => {
+                    (center(last) + center(pos)) / 2.0
+                }
+                RouteEnd::Loop(_) => center(last),
+            };
+
+            let points = std::iter::once(route.source)
+                .chain(route.tiles.iter().map(|(pos, _)| *pos))
+                .map(center)
+                .chain(std::iter::once(end))
+                .collect::<Vec<_>>();
+            for pair in points.windows(2) {
+                draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 2.0, color);
+            }
+
+            if route.end.is_problem() {
+                // red
+                let mut color = hexcolor(0xff5277_ff);
+                color.a = 0.6 + (macroquad::time::get_time() * 4.0).sin() as f32 * 0.3;
+                let size = 3.0;
+                draw_line(
+                    end.x - size,
+                    end.y - size,
+                    end.x + size,
+                    end.y + size,
+                    2.0,
+                    color,
+                );
+                draw_line(
+                    end.x - size,
+                    end.y + size,
+                    end.x + size,
+                    end.y - size,
+                    2.0,
+                    color,
+                );
+            }
+        }
     }
 }
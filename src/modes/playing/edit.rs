@@ -0,0 +1,136 @@
+//! Changing a cable in place, without dragging it out again.
+//!
+//! None of these are allowed to change an end that's connected to something,
+//! so they never break a connection `handle_selection` made.
+
+use ahash::AHashSet;
+use cogs_gamedev::grids::{Direction4, ICoord, Rotation};
+
+use crate::simulator::{
+    board::Board,
+    lint::{self, EndConnection},
+    transport::{Cable, CableKind},
+};
+
+/// Get the ends of the cable at `pos` that are connected to something.
+fn connected_ends(pos: ICoord, cable: &Cable, board: &Board) -> Vec<(Direction4, CableKind)> {
+    cable
+        .cable_outputs()
+        .iter()
+        .filter_map(|(dir, kind)| {
+            let kind = (*kind)?;
+            match lint::end_connection(pos, dir, &board.cables, board) {
+                EndConnection::Cable | EndConnection::Port => Some((dir, kind)),
+                EndConnection::Nothing | EndConnection::Hole => None,
+            }
+        })
+        .collect()
+}
+
+/// Turn the cable at `pos` a quarter turn clockwise.
+///
+/// Returns `false` if there's no cable there, or turning it would disconnect
+/// something or point it into a hole.
+pub fn rotate_cable(board: &mut Board, pos: ICoord) -> bool {
+    let cable = match board.cables.get(&pos) {
+        Some(it) => it,
+        None => return false,
+    };
+    let rotated = cable.rotated(Rotation::Clockwise);
+    let outputs = rotated.cable_outputs();
+
+    let keeps_connections = connected_ends(pos, cable, board)
+        .iter()
+        .all(|(dir, kind)| outputs[*dir] == Some(*kind));
+    let into_hole = outputs.iter().any(|(dir, kind)| {
+        kind.is_some()
+            && lint::end_connection(pos, dir, &board.cables, board) == EndConnection::Hole
+    });
+
+    if keeps_connections && !into_hole {
+        board.cables.insert(pos, rotated);
+        true
+    } else {
+        false
+    }
+}
+
+/// Switch the cable at `pos` between pipe and wire,
+/// along with every cable joined to it end-to-end so the run stays one kind.
+/// On a crossover this does both runs through it.
+///
+/// Returns `false` if there's no cable there or the run is plugged into a port,
+/// because the port decides what kind of cable it needs.
+pub fn retype_run(board: &mut Board, pos: ICoord) -> bool {
+    if !board.cables.contains_key(&pos) {
+        return false;
+    }
+
+    // Each piece is a position, and for crossovers which half of it.
+    let mut pieces = AHashSet::new();
+    // Positions and the direction we came into them going
+    let mut todo: Vec<(ICoord, Option<Direction4>)> = vec![(pos, None)];
+    while let Some((pos, entered)) = todo.pop() {
+        let cable = &board.cables[&pos];
+        let ends = match (cable, entered) {
+            // Only go along the half we came in on
+            (Cable::Crossover { .. }, Some(dir)) => vec![dir, dir.flip()],
+            _ => cable
+                .cable_outputs()
+                .iter()
+                .filter(|(_, kind)| kind.is_some())
+                .map(|(dir, _)| dir)
+                .collect(),
+        };
+        for dir in ends {
+            let piece = match cable {
+                Cable::Crossover { .. } => (pos, Some(dir.is_horizontal())),
+                _ => (pos, None),
+            };
+            pieces.insert(piece);
+
+            match lint::end_connection(pos, dir, &board.cables, board) {
+                EndConnection::Port => return false,
+                EndConnection::Cable => {
+                    let next = pos + dir;
+                    let next_piece = match board.cables[&next] {
+                        Cable::Crossover { .. } => (next, Some(dir.is_horizontal())),
+                        _ => (next, None),
+                    };
+                    if !pieces.contains(&next_piece) {
+                        todo.push((next, Some(dir)));
+                    }
+                }
+                EndConnection::Nothing | EndConnection::Hole => {}
+            }
+        }
+    }
+
+    let toggle = |kind: &mut CableKind| {
+        *kind = match kind {
+            CableKind::Pipe => CableKind::Wire,
+            CableKind::Wire => CableKind::Pipe,
+        }
+    };
+    for (pos, half) in pieces {
+        match (board.cables.get_mut(&pos), half) {
+            (Some(Cable::Straight { kind, .. } | Cable::Bent { kind, .. }), _) => toggle(kind),
+            (Some(Cable::Crossover { horiz_kind, .. }), Some(true)) => toggle(horiz_kind),
+            (Some(Cable::Crossover { vert_kind, .. }), Some(false)) => toggle(vert_kind),
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Swap which half of the crossover at `pos` is drawn on top.
+///
+/// Returns `false` if there isn't a crossover there.
+pub fn swap_layers(board: &mut Board, pos: ICoord) -> bool {
+    if let Some(Cable::Crossover { horiz_on_top, .. }) = board.cables.get_mut(&pos) {
+        *horiz_on_top = !*horiz_on_top;
+        true
+    } else {
+        false
+    }
+}
@@ -1,6 +1,10 @@
 mod drawer;
+mod edit;
+mod region;
 mod simulating;
 
+use std::path::{Path, PathBuf};
+
 use ahash::AHashMap;
 use cogs_gamedev::{
     controls::InputHandler,
@@ -9,35 +13,68 @@ use cogs_gamedev::{
 use macroquad::prelude::info;
 
 use crate::{
-    assets::{Assets, Level},
+    assets::{Assets, Level, LevelSet},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
     simulator::{
         board::Board,
-        solutions::Solution,
-        transport::{Cable, OmniversalConnector, Port, Resource},
+        lint::{self, Diagnostic, EndConnection},
+        routes::{self, Route},
+        solutions::{Metrics, Solution},
+        transport::{Cable, Port, Resource},
     },
-    utils::{draw::draw_space, profile::Profile},
+    utils::{draw::draw_space, profile::Profile, view::View},
     HEIGHT, WIDTH,
 };
 
 use self::{
     drawer::Drawer,
-    simulating::{AdvanceMethod, ModeSimulating, STEP_TIME},
+    region::{Clipboard, Region},
+    simulating::{AdvanceMethod, ModeSimulating},
 };
 
+/// Tiles per frame the cursor moves with the stick all the way over.
+const STICK_SPEED: f32 = 0.25;
+
 pub struct ModePlaying {
     board: Board,
 
     /// Where the cursor is hovering
     cursor: ICoord,
+    /// Where the mouse was last frame, so it only moves the cursor when it moves.
+    /// Otherwise it would clobber anything done with the keyboard.
+    last_mouse: ICoord,
+    /// How far the gamepad stick has pushed the cursor towards the next tile over.
+    stick_travel: (f32, f32),
+    /// Zoom and pan
+    view: View,
     selection: Option<Selection>,
 
+    /// Rectangle picked out for copying
+    region: Option<Region>,
+    clipboard: Option<Clipboard>,
+    /// If this is `true`, the clipboard follows the cursor waiting to be pasted
+    pasting: bool,
+
     start_time: f64,
 
     level_key: String,
     level_idx: usize,
+    /// What list of levels the level is in
+    level_set: LevelSet,
     level_name: String,
+    /// What the level's par is, for the win screen
+    par: Option<Metrics>,
+
+    /// Where each source's cables lead right now
+    routes: Vec<Route>,
+    /// Warnings about the board. Empty while dragging, because a half-laid
+    /// cable always has a loose end.
+    diagnostics: Vec<Diagnostic>,
+    /// If this is `true` the cables changed this frame, so the routes and warnings
+    /// need working out again.
+    /// They're only redone then, instead of every frame.
+    checks_stale: bool,
 }
 
 /// Info about dragging pipes around.
@@ -53,42 +90,145 @@ pub struct Selection {
 }
 
 impl ModePlaying {
-    pub fn new(level: &Level, level_idx: usize) -> Self {
-        let mut profile = Profile::get();
-        let solution = profile
-            .solutions
-            .entry(level.filename.clone())
-            .or_insert_with(|| Solution {
-                cables: level.starting_board.cables.clone(),
-                left: level.starting_board.left.clone(),
-                right: level.starting_board.right.clone(),
-                metrics: None,
-                level_key: level.filename.clone(),
-            })
-            .clone();
-        let board = Board {
-            cables: solution.cables,
-            left: solution.left,
-            right: solution.right,
-            width: level.starting_board.width,
+    /// Pick up where the player left off, or start the level fresh.
+    pub fn new(level: &Level, level_idx: usize, level_set: LevelSet) -> Self {
+        let board = match Profile::get().solutions.get(&level.key()) {
+            // If the level changed out from under it, leave it be; it just doesn't go on this board
+            Some(solution) if solution.fits(&level.starting_board) => Board {
+                cables: solution.cables.clone(),
+                connectors: solution.connectors.clone(),
+                ..level.starting_board.clone()
+            },
+            _ => level.starting_board.clone(),
         };
-        let cursor = ICoord::new(board.width as isize / 2, board.height() as isize / 2);
+        ModePlaying::with_board(level, level_idx, level_set, board)
+    }
+
+    fn with_board(level: &Level, level_idx: usize, level_set: LevelSet, board: Board) -> Self {
+        let cursor = ICoord::new(board.width as isize / 2, board.height as isize / 2);
+        // Leave room for the connectors around the edge
+        let view = View::fitting(
+            (board.width + 2) as f32 * 16.0,
+            (board.height + 2) as f32 * 16.0,
+        );
+        let last_mouse = board.mouse_pos(&view);
 
-        ModePlaying {
+        let mut out = ModePlaying {
             board,
             cursor,
+            last_mouse,
+            stick_travel: (0.0, 0.0),
+            view,
             selection: None,
+            region: None,
+            clipboard: None,
+            pasting: false,
             start_time: macroquad::time::get_time(),
-            level_key: level.filename.clone(),
+            level_key: level.key(),
             level_idx,
+            level_set,
             level_name: level.name.clone(),
+            par: level.par.clone(),
+            routes: Vec::new(),
+            diagnostics: Vec::new(),
+            checks_stale: false,
+        };
+        out.recheck();
+        out
+    }
+
+    /// Get the board as the player sees it, with the cables being dragged around
+    /// if there are any.
+    fn shown_board(&self) -> Board {
+        match &self.selection {
+            Some(sel) => Board {
+                cables: sel.cables.clone(),
+                ..self.board.clone()
+            },
+            None => self.board.clone(),
+        }
+    }
+
+    /// Work out the routes and warnings again.
+    fn recheck(&mut self) {
+        let board = self.shown_board();
+        self.routes = routes::trace_routes(&board);
+        self.diagnostics = if self.selection.is_some() {
+            Vec::new()
+        } else {
+            lint::lint(&board)
+        };
+        self.checks_stale = false;
+    }
+
+    /// Get where the player is pointing this frame.
+    ///
+    /// The direction keys nudge the cursor one tile over, and so does the
+    /// gamepad stick once it's been held long enough;
+    /// otherwise the mouse moves it, but only if it's moved.
+    fn pointed_pos(&mut self, controls: &InputSubscriber) -> ICoord {
+        let key_dir = [
+            (Control::Up, Direction4::North),
+            (Control::Down, Direction4::South),
+            (Control::Left, Direction4::West),
+            (Control::Right, Direction4::East),
+        ]
+        .iter()
+        .find_map(|(control, dir)| {
+            if controls.clicked_down(*control) {
+                Some(*dir)
+            } else {
+                None
+            }
+        });
+
+        let (sx, sy) = controls.stick();
+        let travel = &mut self.stick_travel;
+        // Let go of the stick to stop immediately
+        travel.0 = if sx == 0.0 {
+            0.0
+        } else {
+            travel.0 + sx * STICK_SPEED
+        };
+        travel.1 = if sy == 0.0 {
+            0.0
+        } else {
+            travel.1 + sy * STICK_SPEED
+        };
+        let stick_dir = if travel.0 >= 1.0 {
+            travel.0 -= 1.0;
+            Some(Direction4::East)
+        } else if travel.0 <= -1.0 {
+            travel.0 += 1.0;
+            Some(Direction4::West)
+        } else if travel.1 >= 1.0 {
+            travel.1 -= 1.0;
+            Some(Direction4::South)
+        } else if travel.1 <= -1.0 {
+            travel.1 += 1.0;
+            Some(Direction4::North)
+        } else {
+            None
+        };
+
+        let mouse = self.board.mouse_pos(&self.view);
+        let moved_mouse = mouse != self.last_mouse;
+        self.last_mouse = mouse;
+
+        if let Some(dir) = key_dir.or(stick_dir) {
+            self.cursor + dir
+        } else if moved_mouse {
+            mouse
+        } else {
+            self.cursor
         }
     }
 
     fn handle_selection(&mut self, controls: &InputSubscriber) {
+        let pointed = self.pointed_pos(controls);
         match &mut self.selection {
             None => {
-                let maybe_cursor = self.board.mouse_pos();
+                let maybe_cursor = pointed;
                 if controls.clicked_down(Control::Select) {
                     info!("Clicked at {:?}", maybe_cursor);
                 }
@@ -103,7 +243,8 @@ impl ModePlaying {
                             self.selection = Some(Selection {
                                 prev_info: Vec::new(),
                                 cables: self.board.cables.clone(),
-                            })
+                            });
+                            self.checks_stale = true;
                         }
                     }
                 }
@@ -116,7 +257,7 @@ impl ModePlaying {
                     save_current = true;
                 } else {
                     // ok let's try to keep dragging things around
-                    let maybe_cursor = self.board.mouse_pos();
+                    let maybe_cursor = pointed;
                     // How far did the cursor move?
                     let dcursor = maybe_cursor + ICoord::new(-self.cursor.x, -self.cursor.y);
                     let dir = match (dcursor.x, dcursor.y) {
@@ -128,6 +269,7 @@ impl ModePlaying {
                     };
                     if let Some(dir) = dir {
                         // We moved by an OK direction
+                        self.checks_stale = true;
                         let current_cable = selection.cables.get(&self.cursor);
 
                         #[derive(PartialEq, Eq)]
@@ -199,6 +341,7 @@ impl ModePlaying {
                                 if let Cable::Crossover {
                                     horiz_kind,
                                     vert_kind,
+                                    ..
                                 } = current_cable
                                 {
                                     // turn this into a singlet cable
@@ -243,11 +386,7 @@ impl ModePlaying {
                                     (_, None) => true,
                                     // Only allow outwards from a port
                                     (None, _) if current_port.is_some() => {
-                                        if self.cursor.x == -1 {
-                                            dir == Direction4::East
-                                        } else {
-                                            dir == Direction4::West
-                                        }
+                                        matches!(current_port, Some((_, port_dir)) if port_dir == dir)
                                     }
                                     _ => unreachable!(
                                         "At least one should be Some: {:?}, {:?}",
@@ -276,6 +415,7 @@ impl ModePlaying {
                                         Some(Cable::Crossover {
                                             horiz_kind,
                                             vert_kind,
+                                            ..
                                         }) => {
                                             if dir.is_horizontal() {
                                                 *horiz_kind
@@ -397,6 +537,8 @@ impl ModePlaying {
                                                         Cable::Crossover {
                                                             horiz_kind: h,
                                                             vert_kind: v,
+                                                            // The new cable goes over the old one
+                                                            horiz_on_top: dir.is_horizontal(),
                                                         },
                                                     );
                                                     (true, false)
@@ -428,6 +570,7 @@ impl ModePlaying {
                                 // uh oh I don't know how we got here but it sure isn't valid
                                 // this means we're not selecting a cable nor a port?
                                 self.selection = None;
+                                self.checks_stale = true;
                             }
                         } else if continue_adding == Continue::MergeSelection {
                             // we backtracked all the way to a port!
@@ -443,22 +586,124 @@ impl ModePlaying {
                 if save_current {
                     let sel = self.selection.take().unwrap();
                     self.board.cables = sel.cables;
-
-                    let mut profile = Profile::get();
-                    profile.solutions.insert(
-                        self.level_key.clone(),
-                        Solution {
-                            level_key: self.level_key.clone(),
-                            cables: self.board.cables.clone(),
-                            left: self.board.left.clone(),
-                            right: self.board.right.clone(),
-                            metrics: None,
-                        },
-                    );
+                    self.save_solution();
                 }
             }
         }
     }
+
+    /// Rotate, retype or swap the layers of the cable under the cursor.
+    fn handle_cable_edits(&mut self, controls: &InputSubscriber) {
+        if self.selection.is_some() {
+            return;
+        }
+
+        let edited = if controls.clicked_down(Control::Rotate) {
+            edit::rotate_cable(&mut self.board, self.cursor)
+        } else if controls.clicked_down(Control::Retype) {
+            edit::retype_run(&mut self.board, self.cursor)
+        } else if controls.clicked_down(Control::SwapLayers) {
+            edit::swap_layers(&mut self.board, self.cursor)
+        } else {
+            false
+        };
+        if edited {
+            self.save_solution();
+        }
+    }
+
+    /// Pick out a rectangle with the advanced key, then copy or cut it.
+    fn handle_region(&mut self, controls: &InputSubscriber) {
+        let pointed = self.pointed_pos(controls);
+        if self.board.is_in_board(pointed) {
+            self.cursor = pointed;
+        }
+
+        let region = match &mut self.region {
+            Some(it) => it,
+            None => {
+                // Just started
+                self.region = Some(Region::new(self.cursor));
+                return;
+            }
+        };
+
+        if !region.finished {
+            region.end = self.cursor;
+            if controls.clicked_down(Control::Select) {
+                region.finished = true;
+            }
+        } else if controls.clicked_down(Control::Copy) || controls.clicked_down(Control::Cut) {
+            let region = self.region.take().unwrap();
+            if let Some(clipboard) = Clipboard::copy(&self.board.cables, &region) {
+                self.clipboard = Some(clipboard);
+            }
+            if controls.clicked_down(Control::Cut) {
+                self.board.cables.retain(|pos, _| !region.contains(*pos));
+                self.save_solution();
+            }
+        } else if controls.clicked_down(Control::Select) {
+            // Start again, or stop
+            self.region = if controls.pressed(Control::Ctrl) {
+                Some(Region::new(self.cursor))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Move the clipboard around, turning and flipping it, and paste it down.
+    fn handle_pasting(&mut self, controls: &InputSubscriber) {
+        let pointed = self.pointed_pos(controls);
+        if self.board.is_in_board(pointed) {
+            self.cursor = pointed;
+        }
+
+        let clipboard = match &mut self.clipboard {
+            Some(it) => it,
+            None => {
+                self.pasting = false;
+                return;
+            }
+        };
+        if controls.clicked_down(Control::Rotate) {
+            *clipboard = clipboard.rotated();
+        }
+        if controls.clicked_down(Control::Mirror) {
+            *clipboard = clipboard.mirrored();
+        }
+
+        if controls.clicked_down(Control::Select) {
+            if clipboard.can_paste(self.cursor, &self.board) {
+                // Keep pasting after, so a pattern can be stamped down several times
+                let placed = clipboard
+                    .placed_at(self.cursor)
+                    .map(|(pos, cable)| (pos, cable.clone()))
+                    .collect::<Vec<_>>();
+                self.board.cables.extend(placed);
+                self.save_solution();
+            } else {
+                info!("Can't paste at {:?}", self.cursor);
+            }
+        }
+    }
+
+    /// Write the board's cables to the profile.
+    ///
+    /// This is called whenever they change, so the routes and warnings get redone too.
+    fn save_solution(&mut self) {
+        self.checks_stale = true;
+        let mut profile = Profile::get();
+        profile.solutions.insert(
+            self.level_key.clone(),
+            Solution {
+                level_key: self.level_key.clone(),
+                cables: self.board.cables.clone(),
+                connectors: self.board.connectors.clone(),
+                metrics: None,
+            },
+        );
+    }
 }
 
 impl Gamemode for ModePlaying {
@@ -469,7 +714,20 @@ impl Gamemode for ModePlaying {
         assets: &Assets,
     ) -> Transition {
         if controls.clicked_down(Control::Escape) {
-            return Transition::Pop;
+            return if self.selection.is_some() {
+                // Cancel the drag, leaving the board how it was before
+                self.selection = None;
+                self.recheck();
+                Transition::None
+            } else if self.pasting {
+                self.pasting = false;
+                Transition::None
+            } else if self.region.is_some() {
+                self.region = None;
+                Transition::None
+            } else {
+                Transition::Pop
+            };
         }
 
         let method = if controls.clicked_down(Control::StepOnce) {
@@ -477,7 +735,6 @@ impl Gamemode for ModePlaying {
         } else if controls.clicked_down(Control::Start) {
             Some(AdvanceMethod::ByFrames {
                 start_frame: frame_info.frames_ran,
-                interval: STEP_TIME,
             })
         } else {
             None
@@ -490,7 +747,31 @@ impl Gamemode for ModePlaying {
             )));
         }
 
-        self.handle_selection(controls);
+        self.view.update(controls);
+
+        if self.selection.is_none()
+            && self.clipboard.is_some()
+            && controls.clicked_down(Control::Paste)
+        {
+            self.region = None;
+            self.pasting = true;
+        }
+
+        if self.pasting {
+            self.handle_pasting(controls);
+        } else if self.selection.is_none()
+            && (self.region.is_some()
+                || (controls.pressed(Control::Ctrl) && controls.clicked_down(Control::Select)))
+        {
+            self.handle_region(controls);
+        } else {
+            self.handle_cable_edits(controls);
+            self.handle_selection(controls);
+        }
+
+        if self.checks_stale {
+            self.recheck();
+        }
 
         Transition::None
     }
@@ -498,6 +779,31 @@ impl Gamemode for ModePlaying {
     fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
         Box::new(Drawer::new(&self))
     }
+
+    fn on_assets_reloaded(&mut self, assets: &Assets, changed: &[PathBuf]) {
+        if !is_level_file(&self.level_key, changed) {
+            return;
+        }
+        let found = self
+            .level_set
+            .levels(assets)
+            .iter()
+            .enumerate()
+            .find(|(_, level)| level.key() == self.level_key);
+        if let Some((idx, level)) = found {
+            // Show the new layout as it is.
+            // Whatever's saved stays saved, and comes back next time if it still fits.
+            *self =
+                ModePlaying::with_board(level, idx, self.level_set, level.starting_board.clone());
+        }
+    }
+}
+
+/// Is one of these paths the file for the level with this key?
+fn is_level_file(level_key: &str, paths: &[PathBuf]) -> bool {
+    paths
+        .iter()
+        .any(|path| path.file_stem().map(Path::new) == Some(Path::new(level_key)))
 }
 
 /// Does the cable at the given position have all of its exits used?
@@ -519,20 +825,14 @@ fn is_fully_occupied(
     cables.get(&pos).map(|cable| {
         // Check if none (not any) of the sides are free.
         !cable.cable_outputs().iter().any(|(dir, kind)| {
-            let target_pos = pos + dir;
             if kind.is_some() {
-                if let Some(neighbor) = cables.get(&target_pos) {
-                    let neighbor_conn = neighbor.cable_outputs()[dir.flip()];
-                    // if this is some, we have two cables facing each other
-                    neighbor_conn.is_none()
-                } else if let (true, Some((_, port_dir))) =
-                    (count_ports, port_board.get_port(target_pos))
-                {
-                    // if these are the same, that means these face each other so it's occupied
-                    port_dir != dir.flip()
-                } else {
+                match lint::end_connection(pos, dir, cables, port_board) {
+                    EndConnection::Cable => false,
+                    EndConnection::Port => !count_ports,
                     // this direction is pointing to nothing! and free!
-                    true
+                    EndConnection::Nothing => true,
+                    // nothing can go in a hole in the hull
+                    EndConnection::Hole => false,
                 }
             } else {
                 // There's no output here so it is not free
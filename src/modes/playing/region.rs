@@ -0,0 +1,116 @@
+use ahash::AHashMap;
+use cogs_gamedev::grids::{ICoord, Rotation};
+
+use crate::simulator::{board::Board, transport::Cable};
+
+/// A rectangle of the board, picked out by holding the advanced key and clicking.
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// Where the first click was
+    anchor: ICoord,
+    /// The opposite corner; this follows the cursor until `finished`.
+    pub end: ICoord,
+    /// Has the second corner been clicked?
+    pub finished: bool,
+}
+
+impl Region {
+    pub fn new(anchor: ICoord) -> Self {
+        Self {
+            anchor,
+            end: anchor,
+            finished: false,
+        }
+    }
+
+    /// Get the top-left and bottom-right corners, inclusive.
+    pub fn corners(&self) -> (ICoord, ICoord) {
+        (
+            ICoord::new(self.anchor.x.min(self.end.x), self.anchor.y.min(self.end.y)),
+            ICoord::new(self.anchor.x.max(self.end.x), self.anchor.y.max(self.end.y)),
+        )
+    }
+
+    pub fn contains(&self, pos: ICoord) -> bool {
+        let (min, max) = self.corners();
+        (min.x..=max.x).contains(&pos.x) && (min.y..=max.y).contains(&pos.y)
+    }
+}
+
+/// Some copied cables.
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    /// Positions are relative to the top-left of the copied region.
+    pub cables: Vec<(ICoord, Cable)>,
+    width: isize,
+    height: isize,
+}
+
+impl Clipboard {
+    /// Copy the cables in the region.
+    /// Returns `None` if there aren't any.
+    pub fn copy(cables: &AHashMap<ICoord, Cable>, region: &Region) -> Option<Self> {
+        let (min, max) = region.corners();
+        let copied = cables
+            .iter()
+            .filter(|(pos, _)| region.contains(**pos))
+            .map(|(pos, cable)| (ICoord::new(pos.x - min.x, pos.y - min.y), cable.clone()))
+            .collect::<Vec<_>>();
+        if copied.is_empty() {
+            None
+        } else {
+            Some(Self {
+                cables: copied,
+                width: max.x - min.x + 1,
+                height: max.y - min.y + 1,
+            })
+        }
+    }
+
+    /// Turn the whole thing a quarter turn clockwise.
+    pub fn rotated(&self) -> Self {
+        Self {
+            cables: self
+                .cables
+                .iter()
+                .map(|(pos, cable)| {
+                    (
+                        ICoord::new(self.height - 1 - pos.y, pos.x),
+                        cable.rotated(Rotation::Clockwise),
+                    )
+                })
+                .collect(),
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// Flip the whole thing left-to-right.
+    pub fn mirrored(&self) -> Self {
+        Self {
+            cables: self
+                .cables
+                .iter()
+                .map(|(pos, cable)| (ICoord::new(self.width - 1 - pos.x, pos.y), cable.mirrored()))
+                .collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Get where each cable would go if the top-left was at `corner`.
+    pub fn placed_at(&self, corner: ICoord) -> impl Iterator<Item = (ICoord, &Cable)> + '_ {
+        self.cables
+            .iter()
+            .map(move |(pos, cable)| (corner + *pos, cable))
+    }
+
+    /// Can this be pasted with the top-left at `corner`?
+    ///
+    /// Every cable has to land in the cable area (so not on a port),
+    /// and not on top of any cable already there.
+    pub fn can_paste(&self, corner: ICoord, board: &Board) -> bool {
+        self.placed_at(corner)
+            .all(|(pos, _)| board.is_in_cable_area(pos) && !board.cables.contains_key(&pos))
+    }
+}
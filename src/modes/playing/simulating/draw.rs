@@ -11,8 +11,8 @@ use crate::{
         simulating::{AdvanceMethod, STEP_TIME_ON_DEMAND},
     },
     simulator::{
-        floodfill::FloodFillError,
-        transport::{Cable, CableKind},
+        floodfill::{FloodFillError, TipError},
+        transport::{Cable, CableKind, Port},
     },
     utils::draw::{self, hexcolor},
     HEIGHT, WIDTH,
@@ -26,6 +26,7 @@ impl GamemodeDrawer for ModeSimulating {
 
         draw_space(assets);
 
+        self.view.push_camera(render_targets);
         self.board.draw(assets);
 
         gl_use_material(assets.shaders.cables);
@@ -35,15 +36,15 @@ impl GamemodeDrawer for ModeSimulating {
 
         let dt = frame_info.frames_ran - self.step_start;
         let step_time = match self.advance_method {
-            AdvanceMethod::ByFrames { interval, .. } => interval,
-            AdvanceMethod::OnDemand => STEP_TIME_ON_DEMAND,
+            AdvanceMethod::ByFrames { .. } => self.speed.step_time(),
+            AdvanceMethod::OnDemand => STEP_TIME_ON_DEMAND as f32,
             // whatever
-            _ => 1,
+            _ => 1.0,
         };
-        let tip_progress = (dt as f32 / step_time as f32).clamp(0.0, 1.0);
+        let tip_progress = (dt as f32 / step_time).clamp(0.0, 1.0);
 
         for tip in self.flooder.tips.iter().flatten() {
-            if let Some(cable) = self.board.cables.get(&tip.pos) {
+            if let Some(cable) = self.board.cable_at(tip.pos) {
                 let col = tip.resource.color();
                 // On straight: progress left->right top->bottom
                 // On bent: ccw->cw
@@ -72,6 +73,7 @@ impl GamemodeDrawer for ModeSimulating {
                     Cable::Crossover {
                         horiz_kind,
                         vert_kind,
+                        ..
                     } => {
                         let kind = if tip.facing.is_horizontal() {
                             *horiz_kind
@@ -129,6 +131,7 @@ impl GamemodeDrawer for ModeSimulating {
                     Cable::Crossover {
                         horiz_kind,
                         vert_kind,
+                        ..
                     } => {
                         let kind = if *horiz { *horiz_kind } else { *vert_kind };
                         crossovers.push((
@@ -173,8 +176,15 @@ impl GamemodeDrawer for ModeSimulating {
         }
 
         crossovers.sort_by_key(|(kind, pos, dir, res, progress)| {
-            // sort vertical before horiz so it draws first
-            dir.is_vertical()
+            // sort whichever is on top last so it draws last
+            let horiz_on_top = matches!(
+                self.board.cables.get(pos),
+                Some(Cable::Crossover {
+                    horiz_on_top: true,
+                    ..
+                })
+            );
+            dir.is_horizontal() == horiz_on_top
         });
 
         for (kind, pos, dir, res, progress) in crossovers {
@@ -209,16 +219,9 @@ impl GamemodeDrawer for ModeSimulating {
 
         gl_use_default_material();
 
-        if let AdvanceMethod::Errors(errs) = &self.advance_method {
-            for error in errs {
-                let pos = match error {
-                    FloodFillError::BadCableKind(pos)
-                    | FloodFillError::NoEntrance(pos)
-                    | FloodFillError::SpilledIntoSpace(pos)
-                    | FloodFillError::Backtrack(pos)
-                    | FloodFillError::BadOutput(pos, _) => *pos,
-                };
-                let (cx, cy) = self.board.coord_to_px(pos);
+        if let AdvanceMethod::Errors(errs, panel) = &self.advance_method {
+            for TipError { error, .. } in errs {
+                let (cx, cy) = self.board.coord_to_px(error.pos());
                 let cx = cx + 8.0;
                 let cy = cy + 8.0;
 
@@ -240,6 +243,9 @@ impl GamemodeDrawer for ModeSimulating {
                     FloodFillError::SpilledIntoSpace(_) => 32.0,
                     FloodFillError::Backtrack(_) => 48.0,
                     FloodFillError::BadOutput(_, _) => 64.0,
+                    // 80.0 is the arrow
+                    FloodFillError::Timeout(_) => 96.0,
+                    FloodFillError::Stalled(_) => 112.0,
                 };
                 draw_texture_ex(
                     assets.textures.error_atlas,
@@ -252,6 +258,31 @@ impl GamemodeDrawer for ModeSimulating {
                     },
                 );
             }
+
+            panel.draw_pulse(&self.board);
+        }
+
+        render_targets.pop();
+
+        self.draw_hud(assets);
+
+        // Draw this last so it's on top of everything
+        let mut tooltip = None;
+
+        if let AdvanceMethod::Errors(errs, panel) = &self.advance_method {
+            panel.draw(errs, &self.flooder, assets);
+
+            let hovered = self.board.mouse_pos(&self.view);
+            let explanations = errs
+                .iter()
+                .filter(|err| err.error.pos() == hovered)
+                .map(|err| self.explain_error(err))
+                .collect::<Vec<_>>();
+            if !explanations.is_empty() && !panel.contains_mouse() {
+                let (cx, cy) = self.board.coord_to_px(hovered);
+                let (tx, ty) = self.view.to_canvas((cx + 16.0, cy + 16.0));
+                tooltip = Some((explanations.join("\n\n"), tx, ty));
+            }
         } else if let AdvanceMethod::WinScreen {
             appear_progress,
             text,
@@ -306,6 +337,20 @@ impl GamemodeDrawer for ModeSimulating {
             gl_use_default_material();
         }
 
+        let speed_text = match self.advance_method {
+            AdvanceMethod::ByFrames { .. } => format!("SPEED {}", self.speed.name()),
+            AdvanceMethod::OnDemand => format!("PAUSED (SPEED {})", self.speed.name()),
+            _ => String::new(),
+        };
+        draw::pixel_text(
+            speed_text,
+            8.0,
+            HEIGHT - 14.0,
+            None,
+            hexcolor(0xff5277_ff),
+            assets,
+        );
+
         let text_x = WIDTH / 2.0 - self.level_name.len() as f32 * 4.0 / 2.0;
         draw::pixel_text(
             &self.level_name,
@@ -315,5 +360,77 @@ impl GamemodeDrawer for ModeSimulating {
             hexcolor(0xff5277_ff),
             assets,
         );
+
+        if let Some((text, x, y)) = tooltip {
+            draw::tooltip(&text, x, y, assets);
+        }
+    }
+}
+
+impl ModeSimulating {
+    /// Draw the cycle count, how the tips are doing, and which sinks are happy.
+    fn draw_hud(&self, assets: &Assets) {
+        let moving = self.flooder.tips.iter().filter(|tip| tip.is_some()).count();
+        let delivered = self.flooder.tips.len() - moving;
+
+        let mut lines = vec![
+            format!("CYCLE {}", self.flooder.cycles),
+            format!("TIPS {} MOVING, {} DONE", moving, delivered),
+        ];
+        if let Some(min_cycles) = self.flooder.min_cycles {
+            lines.push(format!("FIRST IN {} CYCLES", min_cycles));
+        }
+
+        lines.push(String::new());
+        lines.push("SINKS".to_owned());
+        for conn in self.board.connectors.iter() {
+            for (idx, port) in conn.ports.iter().enumerate() {
+                if let Some(Port::Sink(res)) = port {
+                    let pos = self.board.port_pos(conn, idx);
+                    let check = if self.flooder.delivered.contains(&pos) {
+                        'X'
+                    } else {
+                        ' '
+                    };
+                    // eg "E2" for the 2nd port on the east
+                    let side = format!("{:?}", conn.side);
+                    lines.push(format!(
+                        "[{}] {}{} {}",
+                        check,
+                        &side[..1],
+                        idx + 1,
+                        res.describe()
+                    ));
+                }
+            }
+        }
+
+        draw::pixel_text(
+            lines.join("\n"),
+            8.0,
+            24.0,
+            None,
+            hexcolor(0xff5277_ff),
+            assets,
+        );
+    }
+
+    /// Explain the error in words, and which tip caused it.
+    fn explain_error(&self, err: &TipError) -> String {
+        match &self.flooder.tips[err.tip] {
+            Some(tip) => {
+                let from = match self.board.get_port(tip.source) {
+                    Some((port, _)) => port.describe(),
+                    None => "SOMEWHERE".to_owned(),
+                };
+                let from = match self.board.describe_slot(tip.source) {
+                    Some(slot) => format!("{} ({})", from, slot),
+                    None => from,
+                };
+                format!("{}\nFROM {}", err.error.describe(&tip.resource), from)
+            }
+            // how did a finished tip go wrong
+            None => format!("{:?}", err.error),
+        }
     }
 }
@@ -0,0 +1,205 @@
+use cogs_gamedev::{controls::InputHandler, grids::ICoord};
+
+use crate::{
+    assets::Assets,
+    controls::{Control, InputCode, InputSubscriber},
+    simulator::{
+        board::Board,
+        floodfill::{FloodFiller, TipError},
+    },
+    utils::draw::{self, mouse_position_pixel},
+    WIDTH,
+};
+
+const PANEL_WIDTH: usize = 6;
+const PANEL_HEIGHT: usize = 12;
+
+const CORNER_X: f32 = WIDTH - PANEL_WIDTH as f32 * 16.0;
+const CORNER_Y: f32 = 24.0;
+
+const TEXT_OFFSET_X: f32 = 6.0;
+const TEXT_OFFSET_Y: f32 = 8.0;
+/// Where the first entry starts, under the header
+const ENTRIES_Y: f32 = CORNER_Y + TEXT_OFFSET_Y + 12.0;
+/// Each entry is three lines, plus a little gap
+const ENTRY_HEIGHT: f32 = 20.0;
+const VISIBLE_ENTRIES: usize = 8;
+
+/// How long a tile pulses for after its entry is clicked, in seconds
+const PULSE_TIME: f64 = 1.5;
+
+/// Scrollable list of everything that went wrong, next to the board.
+#[derive(Clone)]
+pub(crate) struct ErrorPanel {
+    /// Highlighted entry
+    cursor: usize,
+    /// Index of the topmost entry shown
+    scroll: usize,
+    /// Tile being pulsed, and the time it started
+    pulse: Option<(ICoord, f64)>,
+}
+
+impl ErrorPanel {
+    pub fn new() -> Self {
+        Self {
+            cursor: 0,
+            scroll: 0,
+            pulse: None,
+        }
+    }
+
+    pub fn update(&mut self, errors: &[TipError], controls: &InputSubscriber) {
+        if errors.is_empty() {
+            return;
+        }
+
+        let wheel = if self.contains_mouse() {
+            controls.wheel()
+        } else {
+            0.0
+        };
+        if wheel > 0.0 {
+            self.scroll = self.scroll.saturating_sub(1);
+        } else if wheel < 0.0 {
+            self.scroll += 1;
+        }
+
+        if controls.clicked_down(Control::Up) {
+            self.cursor = self.cursor.saturating_sub(1);
+            self.scroll = self.scroll.min(self.cursor);
+        } else if controls.clicked_down(Control::Down) {
+            self.cursor = (self.cursor + 1).min(errors.len() - 1);
+            // keep it in view
+            self.scroll = self
+                .scroll
+                .max((self.cursor + 1).saturating_sub(VISIBLE_ENTRIES));
+        }
+        self.scroll = self
+            .scroll
+            .min(errors.len().saturating_sub(VISIBLE_ENTRIES));
+
+        if controls.clicked_down(Control::Select) {
+            // Clicking goes by where the mouse is; everything else by the highlight
+            let chosen = if matches!(controls.last_input(), Some(InputCode::Mouse(_))) {
+                self.hovered_entry(errors.len())
+            } else {
+                Some(self.cursor)
+            };
+            if let Some(chosen) = chosen {
+                self.cursor = chosen;
+                self.pulse = Some((errors[chosen].error.pos(), macroquad::time::get_time()));
+            }
+        }
+    }
+
+    /// Is the mouse over the panel?
+    pub fn contains_mouse(&self) -> bool {
+        let (mx, my) = mouse_position_pixel();
+        mx >= CORNER_X && my >= CORNER_Y && my < CORNER_Y + PANEL_HEIGHT as f32 * 16.0
+    }
+
+    /// Get the entry the mouse is over, if any.
+    fn hovered_entry(&self, count: usize) -> Option<usize> {
+        let (mx, my) = mouse_position_pixel();
+        if mx < CORNER_X || my < ENTRIES_Y {
+            return None;
+        }
+        let row = ((my - ENTRIES_Y) / ENTRY_HEIGHT) as usize;
+        let idx = self.scroll + row;
+        if row < VISIBLE_ENTRIES && idx < count {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    /// Draw the pulse on the board, if there is one.
+    /// This is in world pixels, so draw it through the view.
+    pub fn draw_pulse(&self, board: &Board) {
+        use macroquad::prelude::*;
+
+        if let Some((pos, start)) = self.pulse {
+            let dt = macroquad::time::get_time() - start;
+            if dt < PULSE_TIME {
+                // three quick flashes, growing outwards
+                let phase = (dt * 3.0 / PULSE_TIME).fract() as f32;
+                let grow = phase * 6.0;
+                let (cx, cy) = board.coord_to_px(pos);
+                draw_rectangle_lines(
+                    cx - grow,
+                    cy - grow,
+                    16.0 + grow * 2.0,
+                    16.0 + grow * 2.0,
+                    2.0,
+                    Color::new(1.0, 0.32, 0.47, 1.0 - phase),
+                );
+            }
+        }
+    }
+
+    pub fn draw(&self, errors: &[TipError], flooder: &FloodFiller, assets: &Assets) {
+        use macroquad::prelude::*;
+
+        gl_use_material(assets.shaders.hologram);
+        assets
+            .shaders
+            .hologram
+            .set_uniform("time", macroquad::time::get_time() as f32);
+
+        draw::patch9(
+            16.0,
+            CORNER_X,
+            CORNER_Y,
+            PANEL_WIDTH,
+            PANEL_HEIGHT,
+            assets.textures.hologram_9patch,
+        );
+
+        let last_shown = (self.scroll + VISIBLE_ENTRIES).min(errors.len());
+        let header = if errors.len() > VISIBLE_ENTRIES {
+            format!(
+                "ERRORS {}-{} OF {}",
+                self.scroll + 1,
+                last_shown,
+                errors.len()
+            )
+        } else {
+            format!("ERRORS ({})", errors.len())
+        };
+        let color = draw::hexcolor(0xff5277_dd);
+        draw::pixel_text(
+            header,
+            CORNER_X + TEXT_OFFSET_X,
+            CORNER_Y + TEXT_OFFSET_Y,
+            None,
+            color,
+            assets,
+        );
+
+        for (row, idx) in (self.scroll..last_shown).enumerate() {
+            let err = &errors[idx];
+            let resource = match &flooder.tips[err.tip] {
+                Some(tip) => tip.resource.describe(),
+                None => "???".to_owned(),
+            };
+            let text = format!(
+                "{}{}. {}\n   CYC {}\n   {}",
+                if idx == self.cursor { ">" } else { " " },
+                idx + 1,
+                err.error.short_name(),
+                err.cycle,
+                resource,
+            );
+            draw::pixel_text(
+                text,
+                CORNER_X + TEXT_OFFSET_X,
+                ENTRIES_Y + row as f32 * ENTRY_HEIGHT,
+                None,
+                color,
+                assets,
+            );
+        }
+
+        gl_use_default_material();
+    }
+}
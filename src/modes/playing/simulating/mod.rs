@@ -1,25 +1,30 @@
 mod draw;
+mod error_panel;
+
+use std::path::PathBuf;
 
 use cogs_gamedev::controls::InputHandler;
 
 use crate::{
-    assets::Assets,
+    assets::{Assets, LevelSet},
     boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
-    modes::ModeEnding,
+    modes::{level_select::solves_needed, ModeEnding},
     simulator::{
         board::Board,
-        floodfill::{FloodFillError, FloodFiller},
+        floodfill::{FloodFiller, TipError},
         solutions::{Metrics, Solution},
         transport::Cable,
     },
-    utils::profile::Profile,
+    utils::{profile::Profile, view::View},
 };
 
+use self::error_panel::ErrorPanel;
+
 use super::ModePlaying;
 
-/// Standard time to do one step in frames.
-pub(super) const STEP_TIME: u64 = 30;
+/// Standard time to do one step in frames, at 1x speed.
+const STEP_TIME: u64 = 30;
 /// Time to do steps when zooming via tab
 const STEP_TIME_ON_DEMAND: u64 = 10;
 
@@ -33,12 +38,19 @@ pub(super) struct ModeSimulating {
     /// going to be bad perf issues from cloning like 1kb
     board: Board,
     flooder: FloodFiller,
+    view: View,
 
     advance_method: AdvanceMethod,
+    /// How fast to go when going by frames
+    speed: Speed,
 
     level_key: String,
     level_idx: usize,
+    level_set: LevelSet,
     level_name: String,
+    par: Option<Metrics>,
+    /// Set when the level file changes on disk, so this is simulating an old layout
+    level_reloaded: bool,
 
     step_start: u64,
 }
@@ -48,11 +60,16 @@ impl ModeSimulating {
         Self {
             board: mode.board.clone(),
             flooder: FloodFiller::new(&mode.board),
+            view: mode.view,
             advance_method,
+            speed: Speed::Normal,
 
             level_key: mode.level_key.clone(),
             level_idx: mode.level_idx,
+            level_set: mode.level_set,
             level_name: mode.level_name.clone(),
+            par: mode.par.clone(),
+            level_reloaded: false,
             step_start: current_frame,
         }
     }
@@ -66,18 +83,27 @@ impl ModeSimulating {
                 self.advance_method = AdvanceMethod::OnDemand;
                 true
             }
-            AdvanceMethod::ByFrames {
-                start_frame,
-                interval,
-            } => {
+            _ if controls.clicked_down(Control::Speed) => {
+                self.speed = self.speed.next();
+                if let AdvanceMethod::ByFrames { start_frame } = &mut self.advance_method {
+                    // Start counting again from here so the new speed lines up
+                    *start_frame = frame_info.frames_ran;
+                }
+                false
+            }
+            AdvanceMethod::ByFrames { start_frame } => {
                 if controls.clicked_down(Control::Start) {
                     // pause
                     self.advance_method = AdvanceMethod::OnDemand;
                     false
-                } else {
-                    // Check if we're on a hot frame
+                } else if let Some(mult) = self.speed.multiplier() {
+                    // Check if we're on a hot frame;
+                    // that's when this frame crosses a multiple of STEP_TIME
                     let dframe = frame_info.frames_ran - start_frame;
-                    dframe % interval == 0
+                    (dframe * mult) % STEP_TIME < mult
+                } else {
+                    // instant is handled elsewhere
+                    false
                 }
             }
             AdvanceMethod::OnDemand => {
@@ -85,7 +111,6 @@ impl ModeSimulating {
                     // back to automatic play
                     self.advance_method = AdvanceMethod::ByFrames {
                         start_frame: frame_info.frames_ran,
-                        interval: STEP_TIME,
                     };
                     true
                 } else {
@@ -100,7 +125,7 @@ impl ModeSimulating {
         if !self.advance_method.is_special() {
             let errors = self.flooder.step(&self.board);
             if !errors.is_empty() {
-                self.advance_method = AdvanceMethod::Errors(errors);
+                self.advance_method = AdvanceMethod::Errors(errors, ErrorPanel::new());
             } else if let Some(metrics) = self.flooder.did_win(&self.board) {
                 // pog
                 self.advance_method = AdvanceMethod::WinScreen {
@@ -108,18 +133,17 @@ impl ModeSimulating {
                     appear_progress: 0.0,
                 };
 
-                let mut profile = Profile::get();
-                let soln = profile
-                    .solutions
-                    .entry(self.level_key.clone())
-                    .or_insert_with(|| Solution {
+                // Whatever's saved might not be what's on the board, if it didn't fit
+                Profile::get().solutions.insert(
+                    self.level_key.clone(),
+                    Solution {
                         cables: self.board.cables.clone(),
-                        left: self.board.left.clone(),
-                        right: self.board.right.clone(),
+                        connectors: self.board.connectors.clone(),
                         level_key: self.level_key.clone(),
-                        metrics: None,
-                    });
-                soln.metrics = Some(metrics);
+                        metrics: Some(metrics),
+                    },
+                );
+                Profile::flush();
             }
         }
     }
@@ -146,11 +170,22 @@ impl ModeSimulating {
             width = chars_across - 11
         );
 
+        // Par goes in the same order as the metrics above it
+        let par_metric = match &self.par {
+            Some(par) => format!(
+                "PAR:{:.>width$}",
+                format!("{}/{}/{}", par.total_cycles, par.min_cycles, par.crossovers),
+                width = chars_across - 4
+            ),
+            None => String::new(),
+        };
+
         format!(
-            "{}\n{}\n{}\n\n\r\r{:^width$}",
+            "{}\n{}\n{}\n{}\n\r\r{:^width$}",
             cycles_metric,
             min_cycles_metric,
             crossover_metric,
+            par_metric,
             "CLICK TO CONTINUE",
             width = chars_across
         )
@@ -164,10 +199,16 @@ impl Gamemode for ModeSimulating {
         frame_info: FrameInfo,
         assets: &Assets,
     ) -> Transition {
-        if controls.clicked_down(Control::Escape) {
+        if controls.clicked_down(Control::Escape) || self.level_reloaded {
             return Transition::Pop;
         }
 
+        // The error panel gets the mouse wheel when it's over it
+        if !matches!(&self.advance_method, AdvanceMethod::Errors(_, panel) if panel.contains_mouse())
+        {
+            self.view.update(controls);
+        }
+
         if let AdvanceMethod::WinScreen {
             appear_progress, ..
         } = &mut self.advance_method
@@ -177,20 +218,42 @@ impl Gamemode for ModeSimulating {
             if *appear_progress > 0.999 && controls.clicked_down(Control::Select) {
                 // TODO: overflows
                 let new_idx = self.level_idx + 1;
-                let trans = if assets.levels.get(new_idx).is_some() {
-                    Box::new(ModePlaying::new(&assets.levels[new_idx], new_idx)) as _
+                let levels = self.level_set.levels(assets);
+                let trans = if let Some(level) = levels.get(new_idx) {
+                    if solves_needed(levels, level, &Profile::get()).is_some() {
+                        // Not yet; go back to the level select and pick something else
+                        Vec::new()
+                    } else {
+                        vec![Box::new(ModePlaying::new(level, new_idx, self.level_set)) as _]
+                    }
+                } else if self.level_set == LevelSet::BuiltIn {
+                    vec![Box::new(ModeEnding::new()) as _]
                 } else {
-                    Box::new(ModeEnding::new()) as _
+                    // Finishing a pack just goes back to its level select
+                    Vec::new()
                 };
                 // Pop this state, and the level select below it
-                return Transition::PopNAndPush(2, vec![trans]);
+                return Transition::PopNAndPush(2, trans);
             }
+        } else if let AdvanceMethod::Errors(errors, panel) = &mut self.advance_method {
+            panel.update(errors, controls);
         } else {
             let advance = self.handle_advance(controls, frame_info);
             if advance {
                 self.step_start = frame_info.frames_ran;
                 self.step();
             }
+
+            if let (AdvanceMethod::ByFrames { .. }, Speed::Instant) =
+                (&self.advance_method, self.speed)
+            {
+                // Skip right to the end.
+                // This always finishes; the flood filler times out if nothing else stops it.
+                while !self.advance_method.is_special() {
+                    self.step();
+                }
+                self.step_start = frame_info.frames_ran;
+            }
         }
 
         Transition::None
@@ -199,16 +262,23 @@ impl Gamemode for ModeSimulating {
     fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
         Box::new(self.clone())
     }
+
+    fn on_assets_reloaded(&mut self, _assets: &Assets, changed: &[PathBuf]) {
+        // The playing mode underneath resets itself
+        if super::is_level_file(&self.level_key, changed) {
+            self.level_reloaded = true;
+        }
+    }
 }
 
 #[derive(Clone)]
 pub(super) enum AdvanceMethod {
-    /// Advance the flood fill once every this many frames
-    ByFrames { start_frame: u64, interval: u64 },
+    /// Advance the flood fill every so many frames, depending on the speed
+    ByFrames { start_frame: u64 },
     /// Advance it on demand when tab is pressed
     OnDemand,
-    /// Wait there were errors!
-    Errors(Vec<FloodFillError>),
+    /// Wait there were errors! (And the panel listing them)
+    Errors(Vec<TipError>, ErrorPanel),
     /// Haha (johnathon) we are not actually stepping, instead here's our win screen
     WinScreen {
         /// Progress from 0-1 how in-view our textbox is
@@ -218,6 +288,59 @@ pub(super) enum AdvanceMethod {
     },
 }
 
+/// How fast to go when advancing by frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Speed {
+    Normal,
+    Double,
+    Quadruple,
+    Sixteen,
+    /// Go straight to the result
+    Instant,
+}
+
+impl Speed {
+    /// How many times faster than `STEP_TIME` this is,
+    /// or `None` for instant.
+    fn multiplier(&self) -> Option<u64> {
+        match self {
+            Speed::Normal => Some(1),
+            Speed::Double => Some(2),
+            Speed::Quadruple => Some(4),
+            Speed::Sixteen => Some(16),
+            Speed::Instant => None,
+        }
+    }
+
+    /// The speed after this one; after instant it goes back to normal.
+    fn next(&self) -> Speed {
+        match self {
+            Speed::Normal => Speed::Double,
+            Speed::Double => Speed::Quadruple,
+            Speed::Quadruple => Speed::Sixteen,
+            Speed::Sixteen => Speed::Instant,
+            Speed::Instant => Speed::Normal,
+        }
+    }
+
+    /// Frames per step at this speed.
+    /// Instant steps take no time at all, but we say 1 so nothing divides by 0.
+    fn step_time(&self) -> f32 {
+        match self.multiplier() {
+            Some(mult) => STEP_TIME as f32 / mult as f32,
+            None => 1.0,
+        }
+    }
+
+    /// Name to show the player.
+    fn name(&self) -> String {
+        match self.multiplier() {
+            Some(mult) => format!("{}X", mult),
+            None => "INSTANT".to_owned(),
+        }
+    }
+}
+
 impl AdvanceMethod {
     /// Returns `true` if the method is special and won't actually advance
     fn is_special(&self) -> bool {
@@ -1,11 +1,14 @@
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use cogs_gamedev::grids::{Direction4, ICoord};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
 use crate::{
     assets::Assets,
     simulator::transport::Resource,
-    utils::draw::{self, mouse_position_pixel},
+    utils::{
+        draw::{self, mouse_position_pixel},
+        view::View,
+    },
     HEIGHT, WIDTH,
 };
 
@@ -14,15 +17,23 @@ use super::transport::{Cable, OmniversalConnector, Port};
 /// The board the game is played on.
 #[derive(Debug, Clone, Deserialize)]
 pub struct Board {
-    /// The connector on the spaceship.
-    pub left: OmniversalConnector,
-    /// The connector on the space station.
-    pub right: OmniversalConnector,
-
-    /// The height of the board is determined by the connectors.
-    /// This is the width; a width of 7 means X-values from 0-6
-    /// can be placed in. (And including the connectors makes it 9, sort of.)
+    /// The connectors on the ships being joined together,
+    /// mounted around the edges of the board.
+    pub connectors: Vec<OmniversalConnector>,
+
+    /// A width of 7 means X-values from 0-6 can be placed in.
+    /// (And including the connectors makes it 9, sort of.)
     pub width: usize,
+    /// Same as the width, but for Y-values.
+    pub height: usize,
+    /// Tiles inside the width and height that aren't part of the hull,
+    /// for boards that aren't a full rectangle.
+    ///
+    /// In the level JSON this is the `mask`, a list of rows where `#` is a cable tile
+    /// and anything else is a hole. Rows can be shorter than the board;
+    /// the missing tiles are cable tiles.
+    #[serde(rename = "mask", default, deserialize_with = "deserialize_mask")]
+    pub holes: AHashSet<ICoord>,
     #[serde(default)]
     pub cables: AHashMap<ICoord, Cable>,
 }
@@ -30,50 +41,65 @@ pub struct Board {
 impl Board {
     /// Get the port (maybe) at the given position, and the side it is facing.
     pub fn get_port(&self, pos: ICoord) -> Option<(&Port, Direction4)> {
-        if pos.y >= 0 {
-            let spot = if pos.x == -1 {
-                self.left.ports.get(pos.y as usize)
-            } else if pos.x == self.width as isize {
-                self.right.ports.get(pos.y as usize)
-            } else {
-                return None;
+        self.connector_slot(pos)
+            .and_then(|(conn, idx)| conn.ports[idx].as_ref().map(|port| (port, conn.facing())))
+    }
+
+    /// Get the connector with a port slot at the given position, and the index
+    /// of the slot. The slot might not have a port in it.
+    pub fn connector_slot(&self, pos: ICoord) -> Option<(&OmniversalConnector, usize)> {
+        self.connectors.iter().find_map(|conn| {
+            // How far along the edge are we, and are we on the right edge at all?
+            let (along, on_edge) = match conn.side {
+                Direction4::West => (pos.y, pos.x == -1),
+                Direction4::East => (pos.y, pos.x == self.width as isize),
+                Direction4::North => (pos.x, pos.y == -1),
+                Direction4::South => (pos.x, pos.y == self.height as isize),
             };
-            spot.map(|x| x.as_ref()).flatten().map(|x| {
-                (
-                    x,
-                    if pos.x == -1 {
-                        Direction4::East
-                    } else {
-                        Direction4::West
-                    },
-                )
-            })
-        } else {
-            None
-        }
+            let idx = along - conn.offset as isize;
+            if on_edge && idx >= 0 && (idx as usize) < conn.ports.len() {
+                Some((conn, idx as usize))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Describe where the port slot at the given position is, for the player.
+    pub fn describe_slot(&self, pos: ICoord) -> Option<String> {
+        self.connector_slot(pos).map(|(conn, idx)| {
+            format!("{:?} CONNECTOR, PORT {}", conn.side, idx + 1).to_uppercase()
+        })
     }
 
-    /// Get the height, or the max of the len of the two connectors.
-    pub fn height(&self) -> usize {
-        self.left.ports.len().max(self.right.ports.len())
+    /// Get the position of the given slot on the given connector.
+    pub fn port_pos(&self, conn: &OmniversalConnector, idx: usize) -> ICoord {
+        let along = (conn.offset + idx) as isize;
+        match conn.side {
+            Direction4::West => ICoord::new(-1, along),
+            Direction4::East => ICoord::new(self.width as isize, along),
+            Direction4::North => ICoord::new(along, -1),
+            Direction4::South => ICoord::new(along, self.height as isize),
+        }
     }
 
-    /// Get where the mouse is in ICoords.
+    /// Get where the mouse is in ICoords, looking through the view.
     /// The center of this board is centered on the window.
-    pub fn mouse_pos(&self) -> ICoord {
-        let (mx, my) = mouse_position_pixel();
+    pub fn mouse_pos(&self, view: &View) -> ICoord {
+        let (mx, my) = view.to_world(mouse_position_pixel());
         let mx = mx - WIDTH / 2.0 - 8.0;
         let my = my - HEIGHT / 2.0 - 8.0;
         let x = (mx / 16.0 + self.width as f32 / 2.0).round() as isize;
-        let y = (my / 16.0 + self.height() as f32 / 2.0).round() as isize;
+        let y = (my / 16.0 + self.height as f32 / 2.0).round() as isize;
         ICoord::new(x, y)
     }
 
     /// Get the pixel coordinate of the upper-left coordinate of the ICoord.
+    /// This is in world pixels; draw it through a `View`.
     pub fn coord_to_px(&self, pos: ICoord) -> (f32, f32) {
         (
             (pos.x as f32 - self.width as f32 / 2.0) * 16.0 + WIDTH / 2.0,
-            (pos.y as f32 - self.height() as f32 / 2.0) as f32 * 16.0 + HEIGHT / 2.0,
+            (pos.y as f32 - self.height as f32 / 2.0) as f32 * 16.0 + HEIGHT / 2.0,
         )
     }
 
@@ -82,15 +108,28 @@ impl Board {
         coord.x >= 0
             && coord.x < self.width as isize
             && coord.y >= 0
-            && coord.y < self.height() as isize
+            && coord.y < self.height as isize
+            && !self.holes.contains(&coord)
+    }
+
+    /// Get the cable at that position, if it's in the cable area.
+    /// Cables in holes in the hull don't count; there's nothing to hold them up.
+    pub fn cable_at(&self, coord: ICoord) -> Option<&Cable> {
+        if self.is_in_cable_area(coord) {
+            self.cables.get(&coord)
+        } else {
+            None
+        }
+    }
+
+    /// Is that position a hole in the hull?
+    pub fn is_hole(&self, coord: ICoord) -> bool {
+        self.holes.contains(&coord)
     }
 
     /// Is that position within the board OR ports?
     pub fn is_in_board(&self, coord: ICoord) -> bool {
-        coord.x >= -1
-            && coord.x <= self.width as isize
-            && coord.y >= 0
-            && coord.y < self.height() as isize
+        self.is_in_cable_area(coord) || self.connector_slot(coord).is_some()
     }
 
     /// Draw only the stuff on the board (including checkerboard, cables and sides.)
@@ -98,8 +137,11 @@ impl Board {
         use macroquad::prelude::*;
 
         for x in 0..self.width as isize {
-            for y in 0..self.height() as isize {
+            for y in 0..self.height as isize {
                 let pos = ICoord::new(x, y);
+                if self.is_hole(pos) {
+                    continue;
+                }
                 let (cx, cy) = self.coord_to_px(pos);
 
                 let color = if (x + y) % 2 == 0 {
@@ -113,11 +155,8 @@ impl Board {
             }
         }
 
-        for (side, left, x) in [
-            (&self.left, true, -1),
-            (&self.right, false, self.width as isize),
-        ] {
-            for (y, port) in side.ports.iter().enumerate() {
+        for conn in self.connectors.iter() {
+            for (idx, port) in conn.ports.iter().enumerate() {
                 if let Some(port) = port {
                     // drawing time
                     let (is_source, res) = match port {
@@ -125,7 +164,12 @@ impl Board {
                         Port::Sink(res) => (false, res),
                     };
 
+                    // The atlas only has east- and west-facing ports,
+                    // so north and south ones are the east-facing ones turned.
+                    let facing = conn.facing();
+                    let left = facing != Direction4::West;
                     let sx = if is_source { 0.0 } else { 16.0 } + if left { 0.0 } else { 32.0 };
+                    let rotation = if left { facing.radians() } else { 0.0 };
                     let (sy, decal) = match res {
                         Resource::Water => (0.0, None),
                         Resource::Fuel => (16.0, None),
@@ -137,7 +181,7 @@ impl Board {
                         }
                     };
 
-                    let pos = ICoord::new(x, y as isize);
+                    let pos = self.port_pos(conn, idx);
                     let (cx, cy) = self.coord_to_px(pos);
 
                     draw_texture_ex(
@@ -147,6 +191,7 @@ impl Board {
                         WHITE,
                         DrawTextureParams {
                             source: Some(Rect::new(sx, sy, 16.0, 16.0)),
+                            rotation,
                             ..Default::default()
                         },
                     );
@@ -179,3 +224,19 @@ impl Board {
         }
     }
 }
+
+/// Read the rows of the `mask` into the set of holes.
+fn deserialize_mask<'de, D: Deserializer<'de>>(de: D) -> Result<AHashSet<ICoord>, D::Error> {
+    let rows = Vec::<String>::deserialize(de)?;
+    let holes = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, c)| *c != '#')
+                .map(move |(x, _)| ICoord::new(x as isize, y as isize))
+        })
+        .collect();
+    Ok(holes)
+}
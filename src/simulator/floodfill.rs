@@ -1,4 +1,4 @@
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use cogs_gamedev::grids::{Direction4, ICoord};
 
 use crate::simulator::transport::{Cable, TransferError};
@@ -9,6 +9,10 @@ use super::{
     transport::{Port, Resource},
 };
 
+/// How many cycles a flood fill can go on for before it gives up.
+/// Every level so far finishes in well under a hundred.
+pub const DEFAULT_MAX_CYCLES: u64 = 1000;
+
 /// This lets us do a floodfill over several frames.
 #[derive(Clone, Debug)]
 pub struct FloodFiller {
@@ -20,13 +24,22 @@ pub struct FloodFiller {
     ///
     /// This is purely for drawing purposes and NOT for the flood-fill itself!
     pub visited: AHashMap<(ICoord, bool), Resource>,
+    /// Every position and direction each tip has been in, by the tip's index.
+    /// A tip's resource never changes, so if it's back in one of these it's going round in a loop.
+    seen: AHashSet<(usize, ICoord, Direction4)>,
+    /// Positions of the sinks that have gotten what they wanted, in the order they got it.
+    pub delivered: Vec<ICoord>,
 
     pub cycles: u64,
     pub min_cycles: Option<u64>,
+    /// Give up with a `Timeout` once it's gone on longer than this.
+    pub max_cycles: u64,
 }
 
 #[derive(Clone, Debug)]
 pub struct Tip {
+    /// Position of the port this came out of
+    pub source: ICoord,
     /// Current position
     pub pos: ICoord,
     /// Direction this entered the current coordinate from.
@@ -39,16 +52,15 @@ impl FloodFiller {
     /// Make a new FloodFiller operating on the given board.
     pub fn new(board: &Board) -> Self {
         let mut tips = Vec::new();
-        for (conn, dir, x) in [
-            // Ports on the left push their stuff east at column 0
-            (&board.left, Direction4::East, 0),
-            // Ports on the right push their stuff west at column (width-1)
-            (&board.right, Direction4::West, board.width - 1),
-        ] {
-            for (y, port) in conn.ports.iter().enumerate() {
+        for conn in board.connectors.iter() {
+            // Ports push their stuff into the tile in front of them
+            let dir = conn.facing();
+            for (idx, port) in conn.ports.iter().enumerate() {
                 if let Some(Port::Source(res)) = port {
+                    let source = board.port_pos(conn, idx);
                     tips.push(Some(Tip {
-                        pos: ICoord::new(x as isize, y as isize),
+                        source,
+                        pos: source + dir,
                         facing: dir,
                         resource: res.clone(),
                     }));
@@ -59,8 +71,11 @@ impl FloodFiller {
         Self {
             tips,
             visited: AHashMap::new(),
+            seen: AHashSet::new(),
+            delivered: Vec::new(),
             cycles: 0,
             min_cycles: None,
+            max_cycles: DEFAULT_MAX_CYCLES,
         }
     }
 
@@ -68,25 +83,40 @@ impl FloodFiller {
     ///
     /// If any problems happened we return them in the vector.
     /// If it's empty, we're all set!
-    pub fn step(&mut self, board: &Board) -> Vec<FloodFillError> {
+    pub fn step(&mut self, board: &Board) -> Vec<TipError> {
         self.cycles += 1;
-
+        if self.cycles > self.max_cycles {
+            return self.error_every_tip(FloodFillError::Timeout);
+        }
         let mut errors = Vec::new();
 
         // clippy is overzealous here
         #[allow(clippy::manual_flatten)]
-        for tip_slot in self.tips.iter_mut() {
+        for (tip_idx, tip_slot) in self.tips.iter_mut().enumerate() {
             if let Some(tip) = tip_slot {
+                // Check this first; a loop comes back over its own cables too
+                if !self.seen.insert((tip_idx, tip.pos, tip.facing)) {
+                    errors.push(TipError {
+                        tip: tip_idx,
+                        cycle: self.cycles,
+                        error: FloodFillError::Stalled(tip.pos),
+                    });
+                    continue;
+                }
                 if self
                     .visited
                     .insert((tip.pos, tip.facing.is_horizontal()), tip.resource.clone())
                     .is_some()
                 {
-                    errors.push(FloodFillError::Backtrack(tip.pos));
+                    errors.push(TipError {
+                        tip: tip_idx,
+                        cycle: self.cycles,
+                        error: FloodFillError::Backtrack(tip.pos),
+                    });
                     continue;
                 }
 
-                if let Some(current_cable) = board.cables.get(&tip.pos) {
+                if let Some(current_cable) = board.cable_at(tip.pos) {
                     let out_dir = match current_cable.exit_dir(&tip.resource, tip.facing) {
                         Ok(it) => it,
                         Err(ono) => {
@@ -96,12 +126,16 @@ impl FloodFiller {
                                 }
                                 TransferError::NoEntrance => FloodFillError::NoEntrance(tip.pos),
                             };
-                            errors.push(err);
+                            errors.push(TipError {
+                                tip: tip_idx,
+                                cycle: self.cycles,
+                                error: err,
+                            });
                             continue;
                         }
                     };
                     let target_pos = tip.pos + out_dir;
-                    if let Some(target_cable) = board.cables.get(&target_pos) {
+                    if let Some(target_cable) = board.cable_at(target_pos) {
                         tip.pos = target_pos;
                         tip.facing = out_dir;
                     } else {
@@ -109,22 +143,35 @@ impl FloodFiller {
                         if let Some((Port::Sink(res), _)) = board.get_port(target_pos) {
                             if res != &tip.resource {
                                 // oh no...
-                                errors.push(FloodFillError::BadOutput(target_pos, res.clone()))
+                                errors.push(TipError {
+                                    tip: tip_idx,
+                                    cycle: self.cycles,
+                                    error: FloodFillError::BadOutput(target_pos, res.clone()),
+                                })
                             } else {
                                 // we are done here poggers
                                 *tip_slot = None;
+                                self.delivered.push(target_pos);
                                 if self.min_cycles.is_none() {
                                     self.min_cycles = Some(self.cycles);
                                 }
                             }
                         } else {
                             // Nope we spill into space
-                            errors.push(FloodFillError::SpilledIntoSpace(target_pos));
+                            errors.push(TipError {
+                                tip: tip_idx,
+                                cycle: self.cycles,
+                                error: FloodFillError::SpilledIntoSpace(target_pos),
+                            });
                         }
                     }
                 } else {
-                    // Really don't know how we got here but uh
-                    errors.push(FloodFillError::SpilledIntoSpace(tip.pos));
+                    // The port is pushing straight into space, or a hole in the hull
+                    errors.push(TipError {
+                        tip: tip_idx,
+                        cycle: self.cycles,
+                        error: FloodFillError::SpilledIntoSpace(tip.pos),
+                    });
                 }
             }
         }
@@ -132,6 +179,21 @@ impl FloodFiller {
         errors
     }
 
+    /// Make the same kind of error for every tip that's still going, where it is.
+    fn error_every_tip(&self, error: fn(ICoord) -> FloodFillError) -> Vec<TipError> {
+        self.tips
+            .iter()
+            .enumerate()
+            .filter_map(|(tip_idx, tip)| {
+                tip.as_ref().map(|tip| TipError {
+                    tip: tip_idx,
+                    cycle: self.cycles,
+                    error: error(tip.pos),
+                })
+            })
+            .collect()
+    }
+
     /// Did we win? If so return our metrics
     pub fn did_win(&self, board: &Board) -> Option<Metrics> {
         if self.tips.iter().all(Option::is_none) {
@@ -150,7 +212,17 @@ impl FloodFiller {
     }
 }
 
-#[derive(Clone)]
+/// A `FloodFillError`, and the tip that caused it.
+#[derive(Clone, Debug)]
+pub struct TipError {
+    /// Index of the tip in `FloodFiller::tips`
+    pub tip: usize,
+    /// The cycle it happened on
+    pub cycle: u64,
+    pub error: FloodFillError,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FloodFillError {
     BadCableKind(ICoord),
     NoEntrance(ICoord),
@@ -160,4 +232,60 @@ pub enum FloodFillError {
     Backtrack(ICoord),
     /// The port didn't like the resource given
     BadOutput(ICoord, Resource),
+    /// It went on for more than `FloodFiller::max_cycles`
+    Timeout(ICoord),
+    /// A tip came back to somewhere it had already been, going the same way,
+    /// so it would go round and round forever
+    Stalled(ICoord),
+}
+
+impl FloodFillError {
+    /// Where did this go wrong?
+    pub fn pos(&self) -> ICoord {
+        match self {
+            FloodFillError::BadCableKind(pos)
+            | FloodFillError::NoEntrance(pos)
+            | FloodFillError::SpilledIntoSpace(pos)
+            | FloodFillError::Backtrack(pos)
+            | FloodFillError::BadOutput(pos, _)
+            | FloodFillError::Timeout(pos)
+            | FloodFillError::Stalled(pos) => *pos,
+        }
+    }
+
+    /// A few words about what went wrong, for when there isn't room to explain.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            FloodFillError::BadCableKind(_) => "WRONG CABLE",
+            FloodFillError::NoEntrance(_) => "NO ENTRANCE",
+            FloodFillError::SpilledIntoSpace(_) => "SPILLED",
+            FloodFillError::Backtrack(_) => "BACKTRACKED",
+            FloodFillError::BadOutput(_, _) => "WRONG PORT",
+            FloodFillError::Timeout(_) => "TIMED OUT",
+            FloodFillError::Stalled(_) => "LOOPED",
+        }
+    }
+
+    /// Explain this to the player, given what the tip was carrying.
+    pub fn describe(&self, carrying: &Resource) -> String {
+        match self {
+            FloodFillError::BadCableKind(_) => {
+                format!("THIS CABLE CAN'T CARRY {}", carrying.describe())
+            }
+            FloodFillError::NoEntrance(_) => "THE CABLE HAS NO OPENING ON THIS SIDE".to_owned(),
+            FloodFillError::SpilledIntoSpace(_) => {
+                format!("{} SPILLED INTO SPACE", carrying.describe())
+            }
+            FloodFillError::Backtrack(_) => "WENT BACK OVER A CABLE IT ALREADY USED".to_owned(),
+            FloodFillError::BadOutput(_, wanted) => format!(
+                "GOT {} BUT WANTED {}",
+                carrying.describe(),
+                wanted.describe()
+            ),
+            FloodFillError::Timeout(_) => {
+                format!("{} TOOK TOO LONG TO GET ANYWHERE", carrying.describe())
+            }
+            FloodFillError::Stalled(_) => format!("{} WENT ROUND IN A LOOP", carrying.describe()),
+        }
+    }
 }
@@ -0,0 +1,426 @@
+//! Golden tests for the flood fill.
+//!
+//! Every level in the manifest has a reference solution here, and the exact metrics it scores.
+//! There are also a few broken boards and the exact errors they run into.
+//! If one of these changes, so does every score in everyone's save file,
+//! so make sure that's what you meant.
+//!
+//! Boards are drawn a row at a time, one character per tile:
+//! - `.` is no cable
+//! - `═ ║ ╔ ╗ ╚ ╝` are pipes, and `─ │ ┌ ┐ └ ┘` are wires
+//! - `╬` and `┼` are crossovers of two pipes or two wires,
+//!   `╪` has a horizontal pipe over a vertical wire, and `╫` is the other way around
+
+use ahash::AHashMap;
+use cogs_gamedev::grids::{Direction4, ICoord};
+
+use crate::assets::{load_builtin_levels, Level};
+
+use super::{
+    board::Board,
+    floodfill::{FloodFillError, FloodFiller, Tip},
+    solutions::Metrics,
+    transport::{Cable, CableKind, Resource},
+};
+
+struct Golden {
+    /// Filename of the level, without the `.json`
+    level: &'static str,
+    cables: &'static [&'static str],
+    metrics: Metrics,
+}
+
+const SOLUTIONS: &[Golden] = &[
+    Golden {
+        level: "tutorial1",
+        cables: &[
+            ".....", //
+            "═════",
+            ".....",
+        ],
+        metrics: Metrics {
+            total_cycles: 5,
+            min_cycles: 5,
+            crossovers: 0,
+        },
+    },
+    Golden {
+        level: "tutorial2",
+        cables: &[
+            "══╗╔", //
+            ".╔╬╝",
+            "═╝╚═",
+        ],
+        metrics: Metrics {
+            total_cycles: 6,
+            min_cycles: 6,
+            crossovers: 1,
+        },
+    },
+    Golden {
+        level: "tutorial3",
+        cables: &[
+            "╔══", //
+            "╫─┐",
+            "╫┐└",
+            "╝└┐",
+            "..└",
+        ],
+        metrics: Metrics {
+            total_cycles: 6,
+            min_cycles: 4,
+            crossovers: 2,
+        },
+    },
+    Golden {
+        level: "level1",
+        cables: &[
+            "══╗", //
+            "═╗║",
+            "╔╬╬",
+            "║║╚",
+            "╝╚═",
+        ],
+        metrics: Metrics {
+            total_cycles: 6,
+            min_cycles: 5,
+            crossovers: 2,
+        },
+    },
+    Golden {
+        level: "level2",
+        cables: &[
+            "──┐", //
+            "┐╔╪",
+            "│║└",
+            "└╫─",
+            "═╝.",
+        ],
+        metrics: Metrics {
+            total_cycles: 6,
+            min_cycles: 5,
+            crossovers: 2,
+        },
+    },
+    Golden {
+        level: "level3",
+        cables: &[
+            ".....", //
+            "──┐┌─",
+            "──┼┘┌",
+            "──┼─┼",
+            "─┐└─┼",
+            "┐└──┼",
+            "└───┘",
+        ],
+        metrics: Metrics {
+            total_cycles: 10,
+            min_cycles: 5,
+            crossovers: 5,
+        },
+    },
+    Golden {
+        level: "level4",
+        cables: &[
+            "──┐╔", //
+            "═╗└╫",
+            "╔╬═╝",
+            "╝╚══",
+            "════",
+        ],
+        metrics: Metrics {
+            total_cycles: 7,
+            min_cycles: 4,
+            crossovers: 2,
+        },
+    },
+    Golden {
+        level: "level5",
+        cables: &[
+            "╗┌─", //
+            "╬╪╗",
+            "╫┘╚",
+            "╝.╔",
+            "══╝",
+        ],
+        metrics: Metrics {
+            total_cycles: 5,
+            min_cycles: 4,
+            crossovers: 3,
+        },
+    },
+    Golden {
+        level: "level6",
+        cables: &[
+            "──┐", //
+            "─┐└",
+            "┐│╔",
+            "└┼╫",
+            "┌┼╫",
+            "┘└╫",
+            "══╝",
+        ],
+        metrics: Metrics {
+            total_cycles: 7,
+            min_cycles: 4,
+            crossovers: 5,
+        },
+    },
+];
+
+/// How running a board to the end went.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Won(Metrics),
+    /// The first errors, with the cycle they happened on and the index of the tip
+    Failed(Vec<(u64, usize, FloodFillError)>),
+}
+
+/// Load the built-in levels the same way the game does,
+/// so a level the game would turn away fails here too.
+fn levels() -> Vec<Level> {
+    let (levels, errors) = load_builtin_levels();
+    assert!(
+        errors.is_empty(),
+        "problems with the built-in levels:\n{}",
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    levels
+}
+
+/// Load the level's board with the drawn cables put on it.
+pub(super) fn board_with(level: &str, rows: &[&str]) -> Board {
+    let level = levels()
+        .into_iter()
+        .find(|it| it.filename == level)
+        .unwrap_or_else(|| panic!("there's no level {}", level));
+    let mut board = level.starting_board;
+    assert_eq!(rows.len(), board.height, "wrong number of rows");
+    for row in rows {
+        assert_eq!(row.chars().count(), board.width, "wrong row width");
+    }
+    board.cables = parse_cables(rows);
+    board
+}
+
+fn parse_cables(rows: &[&str]) -> AHashMap<ICoord, Cable> {
+    use CableKind::{Pipe, Wire};
+    use Direction4::*;
+
+    let mut cables = AHashMap::new();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let straight = |kind, horizontal| Cable::Straight { kind, horizontal };
+            let bent = |kind, ccw_dir| Cable::Bent { kind, ccw_dir };
+            let crossover = |horiz_kind, vert_kind| Cable::Crossover {
+                horiz_kind,
+                vert_kind,
+                horiz_on_top: false,
+            };
+            let cable = match c {
+                '.' => continue,
+                '═' => straight(Pipe, true),
+                '║' => straight(Pipe, false),
+                '─' => straight(Wire, true),
+                '│' => straight(Wire, false),
+                '╚' => bent(Pipe, North),
+                '╔' => bent(Pipe, East),
+                '╗' => bent(Pipe, South),
+                '╝' => bent(Pipe, West),
+                '└' => bent(Wire, North),
+                '┌' => bent(Wire, East),
+                '┐' => bent(Wire, South),
+                '┘' => bent(Wire, West),
+                '╬' => crossover(Pipe, Pipe),
+                '┼' => crossover(Wire, Wire),
+                '╪' => crossover(Pipe, Wire),
+                '╫' => crossover(Wire, Pipe),
+                _ => panic!("unknown cable {:?} at {}, {}", c, x, y),
+            };
+            cables.insert(ICoord::new(x as isize, y as isize), cable);
+        }
+    }
+    cables
+}
+
+/// Flood-fill the board until it wins or errors.
+fn run(board: &Board) -> Outcome {
+    finish(board, FloodFiller::new(board))
+}
+
+/// Keep flood-filling until it wins or errors.
+fn finish(board: &Board, mut flooder: FloodFiller) -> Outcome {
+    loop {
+        let errors = flooder.step(board);
+        if !errors.is_empty() {
+            return Outcome::Failed(
+                errors
+                    .into_iter()
+                    .map(|err| (err.cycle, err.tip, err.error))
+                    .collect(),
+            );
+        } else if let Some(metrics) = flooder.did_win(board) {
+            return Outcome::Won(metrics);
+        }
+    }
+}
+
+fn failed(level: &str, rows: &[&str]) -> Vec<(u64, usize, FloodFillError)> {
+    match run(&board_with(level, rows)) {
+        Outcome::Failed(errors) => errors,
+        Outcome::Won(metrics) => panic!("broken board won with {:?}", metrics),
+    }
+}
+
+pub(super) fn pos(x: isize, y: isize) -> ICoord {
+    ICoord::new(x, y)
+}
+
+#[test]
+fn every_level_has_a_solution() {
+    for level in levels() {
+        assert!(
+            SOLUTIONS
+                .iter()
+                .any(|golden| golden.level == level.filename),
+            "{} has no reference solution",
+            level.filename
+        );
+    }
+}
+
+#[test]
+fn solutions_score_exactly() {
+    for golden in SOLUTIONS {
+        let board = board_with(golden.level, golden.cables);
+        assert_eq!(
+            run(&board),
+            Outcome::Won(golden.metrics.clone()),
+            "in {}",
+            golden.level
+        );
+    }
+}
+
+#[test]
+fn solutions_make_par() {
+    for level in levels() {
+        let file = &level.filename;
+        let (golden, par) = match (SOLUTIONS.iter().find(|g| g.level == file), level.par) {
+            (Some(golden), Some(par)) => (golden, par),
+            _ => continue,
+        };
+        let metrics = &golden.metrics;
+        assert!(
+            metrics.total_cycles <= par.total_cycles
+                && metrics.min_cycles <= par.min_cycles
+                && metrics.crossovers <= par.crossovers,
+            "{} can't make par: {:?} against {:?}",
+            file,
+            metrics,
+            par
+        );
+    }
+}
+
+#[test]
+fn nothing_at_the_source_spills() {
+    let errors = failed("tutorial1", &[".....", ".....", "....."]);
+    assert_eq!(
+        errors,
+        vec![(1, 0, FloodFillError::SpilledIntoSpace(pos(4, 1)))]
+    );
+}
+
+#[test]
+fn gap_in_cable_spills() {
+    let errors = failed("tutorial1", &[".....", "══.══", "....."]);
+    assert_eq!(
+        errors,
+        vec![(2, 0, FloodFillError::SpilledIntoSpace(pos(2, 1)))]
+    );
+}
+
+#[test]
+fn wire_cant_carry_fuel() {
+    let errors = failed("tutorial1", &[".....", "══─══", "....."]);
+    assert_eq!(
+        errors,
+        vec![(3, 0, FloodFillError::BadCableKind(pos(2, 1)))]
+    );
+}
+
+#[test]
+fn cable_facing_the_wrong_way() {
+    let errors = failed("tutorial1", &[".....", "══║══", "....."]);
+    assert_eq!(errors, vec![(3, 0, FloodFillError::NoEntrance(pos(2, 1)))]);
+}
+
+#[test]
+fn sources_meeting_head_on_backtrack() {
+    let errors = failed("tutorial2", &["════", "....", "...."]);
+    assert_eq!(
+        errors,
+        vec![
+            (3, 0, FloodFillError::Backtrack(pos(2, 0))),
+            (3, 1, FloodFillError::Backtrack(pos(1, 0))),
+        ]
+    );
+}
+
+#[test]
+fn crossed_up_sinks() {
+    let errors = failed("tutorial2", &["╗..╔", "║..║", "╝..╚"]);
+    assert_eq!(
+        errors,
+        vec![
+            (3, 0, FloodFillError::BadOutput(pos(-1, 2), Resource::Fuel)),
+            (3, 1, FloodFillError::BadOutput(pos(4, 2), Resource::Water)),
+        ]
+    );
+}
+
+#[test]
+fn going_on_too_long_times_out() {
+    let board = board_with("tutorial1", &[".....", "═════", "....."]);
+    let mut flooder = FloodFiller::new(&board);
+    flooder.max_cycles = 3;
+    assert_eq!(
+        finish(&board, flooder),
+        Outcome::Failed(vec![(4, 0, FloodFillError::Timeout(pos(1, 1)))])
+    );
+}
+
+#[test]
+fn going_round_in_a_loop_stalls() {
+    let board = board_with("tutorial1", &["╔═══╗", "║...║", "╚═══╝"]);
+    let mut flooder = FloodFiller::new(&board);
+    // Cables only have two ends, so nothing coming out of a port can get into a loop;
+    // put some fuel on the ring by hand instead
+    flooder.tips = vec![Some(Tip {
+        source: pos(-1, 0),
+        pos: pos(1, 0),
+        facing: Direction4::East,
+        resource: Resource::Fuel,
+    })];
+    // All the way round is 12 tiles
+    assert_eq!(
+        finish(&board, flooder),
+        Outcome::Failed(vec![(13, 0, FloodFillError::Stalled(pos(1, 0)))])
+    );
+}
+
+#[test]
+fn hole_next_to_the_port_spills() {
+    let mut board = board_with("tutorial1", &[".....", "═════", "....."]);
+    // Right in front of the source
+    board.holes.insert(pos(4, 1));
+    assert_eq!(
+        run(&board),
+        Outcome::Failed(vec![(1, 0, FloodFillError::SpilledIntoSpace(pos(4, 1)))])
+    );
+}
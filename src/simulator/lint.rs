@@ -0,0 +1,234 @@
+use ahash::{AHashMap, AHashSet};
+use cogs_gamedev::grids::{Direction4, ICoord};
+
+use super::{
+    board::Board,
+    routes::{self, RouteEnd},
+    transport::{Cable, CableKind, Port, Resource},
+};
+
+/// Something that looks wrong with a board, found without simulating it.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Where the problem is
+    pub pos: ICoord,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// This end of the cable doesn't lead anywhere.
+    DanglingEnd(Direction4),
+    /// No route from any source delivers this resource to the sink here.
+    UnreachableSink(Resource),
+    /// This kind of cable is plugged into a port that needs the other kind.
+    WrongKindAtPort(CableKind),
+    /// Only one side of the crossover (or neither) has anything going through it.
+    UnusedCrossover,
+}
+
+impl Problem {
+    /// Explain this to the player.
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::DanglingEnd(dir) => {
+                format!("THE {:?} END OF THIS CABLE LEADS NOWHERE", dir).to_uppercase()
+            }
+            Problem::UnreachableSink(res) => {
+                format!("NOTHING DELIVERS {} HERE", res.describe())
+            }
+            Problem::WrongKindAtPort(kind) => {
+                format!("A {:?} CAN'T CONNECT TO THIS PORT", kind).to_uppercase()
+            }
+            Problem::UnusedCrossover => "THIS CROSSOVER ISN'T CROSSED".to_owned(),
+        }
+    }
+}
+
+/// What one end of a cable is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndConnection {
+    /// Another cable with an end facing this one
+    Cable,
+    /// A port facing this cable
+    Port,
+    /// Nothing, or something that doesn't face this way.
+    Nothing,
+    /// A hole in the hull; nothing could ever go there.
+    Hole,
+}
+
+/// What is the end of the cable at `pos` pointing in `dir` connected to?
+///
+/// `port_board` is used only for the ports and holes.
+pub fn end_connection(
+    pos: ICoord,
+    dir: Direction4,
+    cables: &AHashMap<ICoord, Cable>,
+    port_board: &Board,
+) -> EndConnection {
+    let target_pos = pos + dir;
+    if let Some(neighbor) = cables.get(&target_pos) {
+        // if this is some, we have two cables facing each other
+        if neighbor.cable_outputs()[dir.flip()].is_some() {
+            EndConnection::Cable
+        } else {
+            EndConnection::Nothing
+        }
+    } else if let Some((_, port_dir)) = port_board.get_port(target_pos) {
+        // if these are the same, that means these face each other
+        if port_dir == dir.flip() {
+            EndConnection::Port
+        } else {
+            EndConnection::Nothing
+        }
+    } else if port_board.is_hole(target_pos) {
+        EndConnection::Hole
+    } else {
+        EndConnection::Nothing
+    }
+}
+
+/// Find everything that looks wrong with the board.
+///
+/// Diagnostics come out sorted top to bottom, left to right.
+pub fn lint(board: &Board) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let routes = routes::trace_routes(board);
+
+    for (pos, cable) in board.cables.iter() {
+        for (dir, kind) in cable.cable_outputs() {
+            if kind.is_some()
+                && matches!(
+                    end_connection(*pos, dir, &board.cables, board),
+                    EndConnection::Nothing | EndConnection::Hole
+                )
+            {
+                diagnostics.push(Diagnostic {
+                    pos: *pos,
+                    problem: Problem::DanglingEnd(dir),
+                });
+            }
+        }
+    }
+
+    let delivered = routes
+        .iter()
+        .filter_map(|route| match route.end {
+            RouteEnd::Delivered(pos) => Some(pos),
+            _ => None,
+        })
+        .collect::<AHashSet<_>>();
+    for conn in board.connectors.iter() {
+        for (idx, port) in conn.ports.iter().enumerate() {
+            let port = match port {
+                Some(it) => it,
+                None => continue,
+            };
+            let pos = board.port_pos(conn, idx);
+
+            if let Port::Sink(res) = port {
+                // On a fresh board every sink would light up before the player's done anything,
+                // which is just noise
+                if !delivered.contains(&pos) && !board.cables.is_empty() {
+                    diagnostics.push(Diagnostic {
+                        pos,
+                        problem: Problem::UnreachableSink(res.clone()),
+                    });
+                }
+            }
+
+            // Check what's plugged into it
+            let front = pos + conn.facing();
+            let plugged_kind = board
+                .cables
+                .get(&front)
+                .and_then(|cable| cable.cable_outputs()[conn.facing().flip()]);
+            if let Some(kind) = plugged_kind {
+                if kind != port.get_resource().appropriate_cable() {
+                    diagnostics.push(Diagnostic {
+                        pos: front,
+                        problem: Problem::WrongKindAtPort(kind),
+                    });
+                }
+            }
+        }
+    }
+
+    // (position, horizontal) of every crossover side something goes through
+    let crossed = routes
+        .iter()
+        .flat_map(|route| route.tiles.iter())
+        .map(|(pos, facing)| (*pos, facing.is_horizontal()))
+        .collect::<AHashSet<_>>();
+    for (pos, cable) in board.cables.iter() {
+        if matches!(cable, Cable::Crossover { .. })
+            && !(crossed.contains(&(*pos, true)) && crossed.contains(&(*pos, false)))
+        {
+            diagnostics.push(Diagnostic {
+                pos: *pos,
+                problem: Problem::UnusedCrossover,
+            });
+        }
+    }
+
+    diagnostics.sort_by_key(|diag| (diag.pos.y, diag.pos.x));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::golden::{board_with, pos};
+
+    fn problems(level: &str, rows: &[&str]) -> Vec<(ICoord, Problem)> {
+        lint(&board_with(level, rows))
+            .into_iter()
+            .map(|diag| (diag.pos, diag.problem))
+            .collect()
+    }
+
+    #[test]
+    fn fresh_board_has_nothing_wrong() {
+        assert_eq!(problems("tutorial1", &[".....", ".....", "....."]), vec![]);
+    }
+
+    #[test]
+    fn solved_board_has_nothing_wrong() {
+        assert_eq!(problems("tutorial1", &[".....", "═════", "....."]), vec![]);
+    }
+
+    #[test]
+    fn half_laid_cable() {
+        assert_eq!(
+            problems("tutorial1", &[".....", "═══..", "....."]),
+            vec![
+                (pos(-1, 1), Problem::UnreachableSink(Resource::Fuel)),
+                (pos(2, 1), Problem::DanglingEnd(Direction4::East)),
+            ]
+        );
+    }
+
+    #[test]
+    fn wire_into_a_pipe_port() {
+        assert_eq!(
+            problems("tutorial1", &[".....", "════─", "....."]),
+            vec![
+                (pos(-1, 1), Problem::UnreachableSink(Resource::Fuel)),
+                (pos(4, 1), Problem::WrongKindAtPort(CableKind::Wire)),
+            ]
+        );
+    }
+
+    #[test]
+    fn crossover_going_one_way() {
+        assert_eq!(
+            problems("tutorial1", &[".....", "══╬══", "....."]),
+            vec![
+                (pos(2, 1), Problem::DanglingEnd(Direction4::North)),
+                (pos(2, 1), Problem::DanglingEnd(Direction4::South)),
+                (pos(2, 1), Problem::UnusedCrossover),
+            ]
+        );
+    }
+}
@@ -1,4 +1,10 @@
 pub mod board;
 pub mod floodfill;
+#[cfg(test)]
+mod golden;
+pub mod lint;
+#[cfg(test)]
+mod properties;
+pub mod routes;
 pub mod solutions;
 pub mod transport;
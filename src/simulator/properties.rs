@@ -0,0 +1,292 @@
+//! Property tests for cable geometry and the flood fill.
+//!
+//! `Cable::from_dirs`, `exit_dir` and `cable_outputs` all say which sides of a cable are open,
+//! each in their own `match`. There are few enough cables to check every one of them against
+//! each other. Boards are another story, so those are generated at random.
+
+use ahash::{AHashMap, AHashSet};
+use cogs_gamedev::grids::{Direction4, ICoord, Rotation};
+use proptest::prelude::*;
+
+use super::{
+    board::Board,
+    floodfill::FloodFiller,
+    transport::{Cable, CableKind, OmniversalConnector, Port, Resource, TransferError},
+};
+
+const KINDS: [CableKind; 2] = [CableKind::Pipe, CableKind::Wire];
+
+/// Every cable there is.
+fn all_cables() -> Vec<Cable> {
+    let mut cables = Vec::new();
+    for &kind in KINDS.iter() {
+        for &horizontal in [true, false].iter() {
+            cables.push(Cable::Straight { kind, horizontal });
+        }
+        for &ccw_dir in Direction4::DIRECTIONS.iter() {
+            cables.push(Cable::Bent { kind, ccw_dir });
+        }
+        for &vert_kind in KINDS.iter() {
+            for &horiz_on_top in [true, false].iter() {
+                cables.push(Cable::Crossover {
+                    horiz_kind: kind,
+                    vert_kind,
+                    horiz_on_top,
+                });
+            }
+        }
+    }
+    cables
+}
+
+/// One of each kind of resource.
+fn all_resources() -> Vec<Resource> {
+    vec![
+        Resource::Water,
+        Resource::Fuel,
+        Resource::Electricity(5),
+        Resource::Data(0),
+    ]
+}
+
+/// Run the check on every cable, direction to enter it going, and resource.
+fn for_every_entrance(mut check: impl FnMut(&Cable, Direction4, &Resource)) {
+    for cable in all_cables() {
+        for &dir in Direction4::DIRECTIONS.iter() {
+            for res in all_resources() {
+                check(&cable, dir, &res);
+            }
+        }
+    }
+}
+
+/// Is the opening on that side of the cable (if there is one) able to carry this?
+fn open_for(cable: &Cable, side: Direction4, res: &Resource) -> bool {
+    matches!(cable.cable_outputs()[side], Some(kind) if kind.can_carry(res))
+}
+
+#[test]
+fn entering_where_its_open_succeeds() {
+    for_every_entrance(|cable, dir, res| {
+        let side = dir.flip();
+        let result = cable.exit_dir(res, dir);
+        assert_eq!(
+            result.is_ok(),
+            open_for(cable, side, res),
+            "{:?} entering {:?} going {:?}",
+            res,
+            cable,
+            dir
+        );
+        if let Some(kind) = cable.cable_outputs()[side] {
+            if !kind.can_carry(res) {
+                assert!(
+                    matches!(result, Err(TransferError::BadCableKind)),
+                    "{:?} entering {:?} going {:?} should be the wrong kind",
+                    res,
+                    cable,
+                    dir
+                );
+            }
+        }
+    });
+}
+
+#[test]
+fn exits_are_openings() {
+    for_every_entrance(|cable, dir, res| {
+        if let Ok(out) = cable.exit_dir(res, dir) {
+            assert_ne!(out, dir.flip(), "{:?} turned around in {:?}", res, cable);
+            assert!(
+                open_for(cable, out, res),
+                "{:?} left {:?} going {:?} where it isn't open",
+                res,
+                cable,
+                out
+            );
+        }
+    });
+}
+
+#[test]
+fn cables_work_both_ways() {
+    for_every_entrance(|cable, dir, res| {
+        if let Ok(out) = cable.exit_dir(res, dir) {
+            assert_eq!(
+                cable.exit_dir(res, out.flip()).ok(),
+                Some(dir.flip()),
+                "{:?} going backwards through {:?}",
+                res,
+                cable
+            );
+        }
+    });
+}
+
+#[test]
+fn from_dirs_opens_both_ends() {
+    for &kind in KINDS.iter() {
+        for &dir1 in Direction4::DIRECTIONS.iter() {
+            for &dir2 in Direction4::DIRECTIONS.iter() {
+                if dir1 == dir2 {
+                    continue;
+                }
+                let cable = Cable::from_dirs(kind, dir1, dir2);
+                for &side in Direction4::DIRECTIONS.iter() {
+                    let expected = if side == dir1 || side == dir2 {
+                        Some(kind)
+                    } else {
+                        None
+                    };
+                    assert_eq!(
+                        cable.cable_outputs()[side],
+                        expected,
+                        "{:?} from {:?} and {:?}",
+                        cable,
+                        dir1,
+                        dir2
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn from_dirs_needs_two_directions() {
+    Cable::from_dirs(CableKind::Pipe, Direction4::North, Direction4::North);
+}
+
+#[test]
+fn rotating_turns_the_openings() {
+    for cable in all_cables() {
+        for &rot in [Rotation::Clockwise, Rotation::CounterClockwise].iter() {
+            let rotated = cable.rotated(rot);
+            for &side in Direction4::DIRECTIONS.iter() {
+                assert_eq!(
+                    rotated.cable_outputs()[side.rotate(rot)],
+                    cable.cable_outputs()[side],
+                    "{:?} turned {:?}",
+                    cable,
+                    rot
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn mirroring_flips_the_openings() {
+    let mirror = |dir: Direction4| {
+        if dir.is_horizontal() {
+            dir.flip()
+        } else {
+            dir
+        }
+    };
+    for cable in all_cables() {
+        let mirrored = cable.mirrored();
+        for &side in Direction4::DIRECTIONS.iter() {
+            assert_eq!(
+                mirrored.cable_outputs()[mirror(side)],
+                cable.cable_outputs()[side],
+                "{:?} mirrored",
+                cable
+            );
+        }
+    }
+}
+
+fn direction() -> impl Strategy<Value = Direction4> {
+    prop::sample::select(Direction4::DIRECTIONS.to_vec())
+}
+
+fn resource() -> impl Strategy<Value = Resource> {
+    prop_oneof![
+        Just(Resource::Water),
+        Just(Resource::Fuel),
+        (0..3u8).prop_map(Resource::Electricity),
+        (0..3u8).prop_map(Resource::Data),
+    ]
+}
+
+fn port() -> impl Strategy<Value = Port> {
+    prop_oneof![
+        resource().prop_map(Port::Source),
+        resource().prop_map(Port::Sink),
+    ]
+}
+
+/// Connectors can hang off the ends of the board, or overlap;
+/// the flood fill had better cope with bad level files too.
+fn connector() -> impl Strategy<Value = OmniversalConnector> {
+    (
+        direction(),
+        0..4usize,
+        prop::collection::vec(prop::option::of(port()), 1..8),
+    )
+        .prop_map(|(side, offset, ports)| OmniversalConnector {
+            side,
+            offset,
+            slider: vec![false; ports.len()],
+            ports,
+        })
+}
+
+/// A board with cables strewn all over it, some of them in holes.
+fn board() -> impl Strategy<Value = Board> {
+    (1..8usize, 1..8usize)
+        .prop_flat_map(|(width, height)| {
+            let tiles = width * height;
+            (
+                Just((width, height)),
+                prop::collection::vec(connector(), 0..5),
+                prop::collection::vec(
+                    prop::option::weighted(0.8, prop::sample::select(all_cables())),
+                    tiles,
+                ),
+                prop::collection::vec(prop::bool::weighted(0.1), tiles),
+            )
+        })
+        .prop_map(|((width, height), connectors, cables, holes)| {
+            let coord = |idx: usize| ICoord::new((idx % width) as isize, (idx / width) as isize);
+            Board {
+                connectors,
+                width,
+                height,
+                holes: holes
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, hole)| *hole)
+                    .map(|(idx, _)| coord(idx))
+                    .collect::<AHashSet<_>>(),
+                cables: cables
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(idx, cable)| cable.map(|cable| (coord(idx), cable)))
+                    .collect::<AHashMap<_, _>>(),
+            }
+        })
+}
+
+proptest! {
+    #[test]
+    fn flood_fill_always_finishes(board in board()) {
+        let mut flooder = FloodFiller::new(&board);
+        // Every cycle without errors, some tip goes over a cable it hasn't gone over
+        // that way before, and there are only so many of those.
+        let limit = 2 * (board.width * board.height) as u64 + 1;
+        loop {
+            let errors = flooder.step(&board);
+            if !errors.is_empty() || flooder.did_win(&board).is_some() {
+                break;
+            }
+            prop_assert!(
+                flooder.cycles <= limit,
+                "still going after {} cycles",
+                flooder.cycles
+            );
+        }
+    }
+}
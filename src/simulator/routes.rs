@@ -0,0 +1,150 @@
+use ahash::AHashSet;
+use cogs_gamedev::grids::{Direction4, ICoord};
+
+use super::{
+    board::Board,
+    transport::{Port, Resource},
+};
+
+/// Where each source's resource would go, found by following the cables
+/// instead of running the flood fill.
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// Position of the source port
+    pub source: ICoord,
+    pub resource: Resource,
+    /// Every cable tile along the way, in order,
+    /// and the direction the resource was going when it entered.
+    pub tiles: Vec<(ICoord, Direction4)>,
+    pub end: RouteEnd,
+}
+
+/// How a route stops.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteEnd {
+    /// It made it to a sink that wants it at this position.
+    Delivered(ICoord),
+    /// It made it to a sink that wants something else.
+    WrongSink(ICoord),
+    /// The cable just stops, pointing at this position.
+    Dangling(ICoord),
+    /// There's a cable here, but it doesn't let the resource in.
+    Blocked(ICoord),
+    /// It came back around to this position.
+    Loop(ICoord),
+}
+
+impl RouteEnd {
+    /// Is this end a mistake?
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, RouteEnd::Delivered(_))
+    }
+}
+
+/// Follow the cables out of every source on the board.
+pub fn trace_routes(board: &Board) -> Vec<Route> {
+    let mut routes = Vec::new();
+    for conn in board.connectors.iter() {
+        for (idx, port) in conn.ports.iter().enumerate() {
+            if let Some(Port::Source(res)) = port {
+                let source = board.port_pos(conn, idx);
+                routes.push(trace_route(board, source, conn.facing(), res));
+            }
+        }
+    }
+    routes
+}
+
+/// Follow the cables out of one source, the same way a tip would.
+fn trace_route(board: &Board, source: ICoord, facing: Direction4, resource: &Resource) -> Route {
+    let mut tiles = Vec::new();
+    // Same as the flood filler; crossovers can be visited once each way
+    let mut visited = AHashSet::new();
+
+    let mut pos = source + facing;
+    let mut facing = facing;
+    let end = loop {
+        let cable = match board.cable_at(pos) {
+            Some(it) => it,
+            None => {
+                break match board.get_port(pos) {
+                    Some((Port::Sink(wanted), _)) if wanted == resource => RouteEnd::Delivered(pos),
+                    Some((Port::Sink(_), _)) => RouteEnd::WrongSink(pos),
+                    _ => RouteEnd::Dangling(pos),
+                };
+            }
+        };
+
+        if !visited.insert((pos, facing.is_horizontal())) {
+            break RouteEnd::Loop(pos);
+        }
+        match cable.exit_dir(resource, facing) {
+            Ok(out) => {
+                tiles.push((pos, facing));
+                pos = pos + out;
+                facing = out;
+            }
+            Err(_) => break RouteEnd::Blocked(pos),
+        }
+    };
+
+    Route {
+        source,
+        resource: resource.clone(),
+        tiles,
+        end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::golden::{board_with, pos};
+
+    fn ends(board: &Board) -> Vec<RouteEnd> {
+        trace_routes(board)
+            .into_iter()
+            .map(|route| route.end)
+            .collect()
+    }
+
+    #[test]
+    fn straight_across_delivers() {
+        let routes = trace_routes(&board_with("tutorial1", &[".....", "═════", "....."]));
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].source, pos(5, 1));
+        assert_eq!(
+            routes[0].tiles,
+            (0..5)
+                .rev()
+                .map(|x| (pos(x, 1), Direction4::West))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(routes[0].end, RouteEnd::Delivered(pos(-1, 1)));
+    }
+
+    #[test]
+    fn empty_board_dangles_at_the_source() {
+        let board = board_with("tutorial1", &[".....", ".....", "....."]);
+        assert_eq!(ends(&board), vec![RouteEnd::Dangling(pos(4, 1))]);
+    }
+
+    #[test]
+    fn gap_dangles() {
+        let board = board_with("tutorial1", &[".....", "══.══", "....."]);
+        assert_eq!(ends(&board), vec![RouteEnd::Dangling(pos(2, 1))]);
+    }
+
+    #[test]
+    fn wire_blocks_fuel() {
+        let board = board_with("tutorial1", &[".....", "══─══", "....."]);
+        assert_eq!(ends(&board), vec![RouteEnd::Blocked(pos(2, 1))]);
+    }
+
+    #[test]
+    fn cables_in_holes_dont_count() {
+        let mut board = board_with("tutorial1", &[".....", "═════", "....."]);
+        board.holes.insert(pos(2, 1));
+        assert_eq!(ends(&board), vec![RouteEnd::Dangling(pos(2, 1))]);
+    }
+}
@@ -2,7 +2,10 @@ use ahash::AHashMap;
 use cogs_gamedev::grids::ICoord;
 use serde::{Deserialize, Serialize};
 
-use crate::simulator::transport::{Cable, OmniversalConnector};
+use crate::simulator::{
+    board::Board,
+    transport::{Cable, OmniversalConnector},
+};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Solution {
@@ -11,16 +14,59 @@ pub struct Solution {
     /// Cable layout
     pub cables: AHashMap<ICoord, Cable>,
 
-    pub left: OmniversalConnector,
-    pub right: OmniversalConnector,
+    pub connectors: Vec<OmniversalConnector>,
 
     /// If this is Some, the level is solved!
     pub metrics: Option<Metrics>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+impl Solution {
+    /// Can this still be put on the board?
+    /// Levels can change after a solution is saved,
+    /// leaving cables off the edge or connectors that don't match anymore.
+    pub fn fits(&self, board: &Board) -> bool {
+        self.connectors == board.connectors
+            && self.cables.keys().all(|&pos| board.is_in_cable_area(pos))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Metrics {
     pub total_cycles: u64,
     pub min_cycles: u64,
     pub crossovers: u64,
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assets::load_builtin_levels, simulator::transport::CableKind};
+
+    #[test]
+    fn solutions_stop_fitting_when_levels_change() {
+        let (levels, _) = load_builtin_levels();
+        let board = levels[0].starting_board.clone();
+        let mut cables = AHashMap::new();
+        cables.insert(
+            ICoord::new(board.width as isize - 1, 0),
+            Cable::Straight {
+                kind: CableKind::Pipe,
+                horizontal: true,
+            },
+        );
+        let solution = Solution {
+            level_key: levels[0].key(),
+            cables,
+            connectors: board.connectors.clone(),
+            metrics: None,
+        };
+        assert!(solution.fits(&board));
+
+        let mut narrower = board.clone();
+        narrower.width -= 1;
+        assert!(!solution.fits(&narrower));
+
+        let mut moved = board;
+        moved.connectors[0].offset += 1;
+        assert!(!solution.fits(&moved));
+    }
+}
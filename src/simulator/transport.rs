@@ -31,6 +31,16 @@ impl Resource {
         }
     }
 
+    /// Name to show the player.
+    pub fn describe(&self) -> String {
+        match self {
+            Resource::Water => "WATER".to_owned(),
+            Resource::Fuel => "FUEL".to_owned(),
+            Resource::Electricity(tw) => format!("ELECTRICITY {} GW", tw),
+            Resource::Data(chan) => format!("DATA CHANNEL {}", chan),
+        }
+    }
+
     /// Get the color of this resource.
     pub fn color(&self) -> Color {
         match self {
@@ -68,6 +78,9 @@ pub enum Cable {
     Crossover {
         horiz_kind: CableKind,
         vert_kind: CableKind,
+        /// Is the horizontal cable drawn over the vertical one?
+        /// This is just for looks.
+        horiz_on_top: bool,
     },
 }
 
@@ -110,6 +123,7 @@ impl Cable {
             Cable::Crossover {
                 horiz_kind,
                 vert_kind,
+                ..
             } => {
                 let check_kind = if enter_dir.is_horizontal() {
                     horiz_kind
@@ -146,6 +160,7 @@ impl Cable {
             Cable::Crossover {
                 horiz_kind,
                 vert_kind,
+                ..
             } => {
                 enum_map! {
                     dir => Some(if dir.is_horizontal(){*horiz_kind}else{*vert_kind})
@@ -181,6 +196,54 @@ impl Cable {
         }
     }
 
+    /// Get this cable turned a quarter turn in the given direction.
+    pub fn rotated(&self, rot: Rotation) -> Self {
+        match self {
+            Cable::Straight { kind, horizontal } => Cable::Straight {
+                kind: *kind,
+                horizontal: !*horizontal,
+            },
+            Cable::Bent { kind, ccw_dir } => Cable::Bent {
+                kind: *kind,
+                ccw_dir: ccw_dir.rotate(rot),
+            },
+            // The horizontal part becomes the vertical part and vice versa
+            Cable::Crossover {
+                horiz_kind,
+                vert_kind,
+                horiz_on_top,
+            } => Cable::Crossover {
+                horiz_kind: *vert_kind,
+                vert_kind: *horiz_kind,
+                horiz_on_top: !*horiz_on_top,
+            },
+        }
+    }
+
+    /// Get this cable flipped left-to-right.
+    pub fn mirrored(&self) -> Self {
+        match self {
+            Cable::Bent { kind, ccw_dir } => {
+                let mirror = |dir: Direction4| {
+                    if dir.is_horizontal() {
+                        dir.flip()
+                    } else {
+                        dir
+                    }
+                };
+                // Mirroring swaps which end is the counter-clockwise one,
+                // but `from_dirs` sorts that out.
+                Cable::from_dirs(
+                    *kind,
+                    mirror(*ccw_dir),
+                    mirror(ccw_dir.rotate(Rotation::Clockwise)),
+                )
+            }
+            // These look the same in a mirror
+            Cable::Straight { .. } | Cable::Crossover { .. } => self.clone(),
+        }
+    }
+
     /// Get the sx/sy of this cable in the atlas.
     /// Also, maybe return a second one to be drawn after if this is a
     /// crossover with two cable types
@@ -207,9 +270,9 @@ impl Cable {
             Cable::Crossover {
                 horiz_kind,
                 vert_kind,
+                horiz_on_top,
             } => {
                 // pretend there are two straight cables.
-                // TODO: which looks better on top?
                 let c1 = Cable::Straight {
                     kind: *horiz_kind,
                     horizontal: true,
@@ -218,9 +281,13 @@ impl Cable {
                     kind: *vert_kind,
                     horizontal: false,
                 };
-                let (sxy1, _) = c1.get_slices();
-                let (sxy2, _) = c2.get_slices();
-                (sxy1, Some(sxy2))
+                let (horiz, _) = c1.get_slices();
+                let (vert, _) = c2.get_slices();
+                if *horiz_on_top {
+                    (vert, Some(horiz))
+                } else {
+                    (horiz, Some(vert))
+                }
             }
         }
     }
@@ -254,14 +321,20 @@ pub enum TransferError {
 }
 
 /// The intergalactic "standard" connector.
-/// Each level has 2 (or more?) of these.
+/// Each level has 2 (or more!) of these.
 ///
 /// The length of the port slots will never change,
 /// so please don't push or pop or whatever from the vectors.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OmniversalConnector {
-    /// Ports, starting at the top.
-    /// Index 0 is at y-pos 0, and so on.
+    /// The edge of the board this is mounted on.
+    /// The ports face the other way, into the board.
+    pub side: Direction4,
+    /// How many tiles along the edge the first port is.
+    #[serde(default)]
+    pub offset: usize,
+    /// Ports, starting at the top (or the left, on the north and south edges).
+    /// Index 0 is at `offset`, and so on.
     ///
     /// `None` just means there's no port here.
     pub ports: Vec<Option<Port>>,
@@ -270,8 +343,15 @@ pub struct OmniversalConnector {
     pub slider: Vec<bool>,
 }
 
+impl OmniversalConnector {
+    /// The direction the ports push resources out into the board.
+    pub fn facing(&self) -> Direction4 {
+        self.side.flip()
+    }
+}
+
 /// Different ports in the Omniversal Connectors
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Port {
     /// It produces this resource
     Source(Resource),
@@ -285,4 +365,12 @@ impl Port {
             Port::Source(it) | Port::Sink(it) => it,
         }
     }
+
+    /// Explain this to the player.
+    pub fn describe(&self) -> String {
+        match self {
+            Port::Source(res) => format!("SOURCE: {}", res.describe()),
+            Port::Sink(res) => format!("SINK: {}", res.describe()),
+        }
+    }
 }
@@ -165,6 +165,95 @@ pub fn patch9(
     }
 }
 
+/// Draw a hologram box with the text in it, with its corner at the given position.
+/// It gets nudged over to stay on the screen.
+pub fn tooltip(text: &str, x: f32, y: f32, assets: &Assets) {
+    let cols = text.lines().map(str::len).max().unwrap_or(0);
+    let rows = text.lines().count();
+    // 6px of padding on each side
+    let width = ((cols as f32 * 4.0 + 12.0) / 16.0).ceil().max(2.0) as usize;
+    let height = ((rows as f32 * 6.0 + 12.0) / 16.0).ceil().max(2.0) as usize;
+
+    let ox = x.min(WIDTH - width as f32 * 16.0).max(0.0);
+    let oy = y.min(HEIGHT - height as f32 * 16.0).max(0.0);
+
+    gl_use_material(assets.shaders.hologram);
+    assets
+        .shaders
+        .hologram
+        .set_uniform("time", macroquad::time::get_time() as f32);
+
+    patch9(16.0, ox, oy, width, height, assets.textures.hologram_9patch);
+    pixel_text(
+        text,
+        ox + 6.0,
+        oy + 7.0,
+        None,
+        hexcolor(0xff5277_dd),
+        assets,
+    );
+
+    gl_use_default_material();
+}
+
+/// Size in tiles of the hologram box menus like the options go in
+const MENU_WIDTH: usize = 16;
+const MENU_HEIGHT: usize = 10;
+
+const MENU_CORNER_X: f32 = WIDTH / 2.0 - MENU_WIDTH as f32 * 16.0 / 2.0;
+const MENU_CORNER_Y: f32 = HEIGHT / 2.0 - MENU_HEIGHT as f32 * 16.0 / 2.0;
+
+const MENU_TEXT_OFFSET_X: f32 = 8.0;
+const MENU_TEXT_OFFSET_Y: f32 = 12.0;
+
+/// Height of a line of text
+const ROW_HEIGHT: f32 = 6.0;
+
+/// Draw a menu's hologram box in the middle of the screen with the text in it.
+pub fn menu_box(text: &str, assets: &Assets) {
+    gl_use_material(assets.shaders.hologram);
+    assets
+        .shaders
+        .hologram
+        .set_uniform("time", macroquad::time::get_time() as f32);
+
+    patch9(
+        16.0,
+        MENU_CORNER_X,
+        MENU_CORNER_Y,
+        MENU_WIDTH,
+        MENU_HEIGHT,
+        assets.textures.hologram_9patch,
+    );
+    pixel_text(
+        text,
+        MENU_CORNER_X + MENU_TEXT_OFFSET_X,
+        MENU_CORNER_Y + MENU_TEXT_OFFSET_Y,
+        None,
+        hexcolor(0xff5277_dd),
+        assets,
+    );
+
+    gl_use_default_material();
+}
+
+/// Where that line of text in the menu box is on the screen.
+pub fn menu_line_rect(line: usize) -> Rect {
+    Rect::new(
+        MENU_CORNER_X + MENU_TEXT_OFFSET_X,
+        MENU_CORNER_Y + MENU_TEXT_OFFSET_Y + line as f32 * ROW_HEIGHT,
+        MENU_WIDTH as f32 * 16.0 - MENU_TEXT_OFFSET_X * 2.0,
+        ROW_HEIGHT,
+    )
+}
+
+/// Get which of the `count` rows starting on line `first` of the menu box the mouse is over,
+/// counting from the first one.
+pub fn hovered_menu_row(first: usize, count: usize) -> Option<usize> {
+    let (mx, my) = mouse_position_pixel();
+    (0..count).find(|&row| menu_line_rect(first + row).contains(vec2(mx, my)))
+}
+
 pub fn draw_space(assets: &Assets) {
     use macroquad::prelude::*;
     gl_use_material(assets.shaders.space);
@@ -1,3 +1,4 @@
 pub mod draw;
 pub mod profile;
 pub mod serdeflate;
+pub mod view;
@@ -0,0 +1,231 @@
+//! Bringing profiles saved by older versions of the game up to date.
+//!
+//! Bincode has no field names or defaults, so a profile can only be read back
+//! with exactly the layout it was saved with.
+//! Every old layout is spelled out here, along with how to turn it into the current one.
+//! The pieces that haven't changed since are shared with the current layout.
+
+use ahash::AHashMap;
+use anyhow::{anyhow, Context};
+use cogs_gamedev::grids::{Direction4, ICoord};
+use serde::Deserialize;
+#[cfg(test)]
+use serde::Serialize;
+
+use crate::{
+    controls::InputSubscriber,
+    simulator::{
+        solutions::{Metrics, Solution},
+        transport::{Cable, CableKind, OmniversalConnector, Port},
+    },
+};
+
+use super::Profile;
+
+/// Older versions of the save format we can upgrade from, newest first.
+pub const OLD_VERSIONS: &[&str] = &["5"];
+
+/// Read a profile saved under an older version and bring it up to date.
+pub fn upgrade(version: &str, data: &[u8]) -> anyhow::Result<Profile> {
+    match version {
+        "5" => bincode::deserialize::<ProfileV5>(data)
+            .map(Profile::from)
+            .context("When deserializing from bincode"),
+        _ => Err(anyhow!("Don't know how to upgrade version {}", version)),
+    }
+}
+
+/// Version 5 only had connectors on the left and right edges, both starting at the top,
+/// didn't remember which cable in a crossover is on top, and didn't have a keymap.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct ProfileV5 {
+    pub solutions: AHashMap<String, SolutionV5>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct SolutionV5 {
+    pub level_key: String,
+    pub cables: AHashMap<ICoord, CableV5>,
+    pub left: ConnectorV5,
+    pub right: ConnectorV5,
+    pub metrics: Option<Metrics>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+pub struct ConnectorV5 {
+    pub ports: Vec<Option<Port>>,
+    pub slider: Vec<bool>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+pub enum CableV5 {
+    Straight {
+        kind: CableKind,
+        horizontal: bool,
+    },
+    Bent {
+        kind: CableKind,
+        ccw_dir: Direction4,
+    },
+    Crossover {
+        horiz_kind: CableKind,
+        vert_kind: CableKind,
+    },
+}
+
+impl From<ProfileV5> for Profile {
+    fn from(old: ProfileV5) -> Self {
+        Profile {
+            solutions: old
+                .solutions
+                .into_iter()
+                .map(|(key, soln)| (key, soln.into()))
+                .collect(),
+            // The controls were always the defaults
+            keymap: InputSubscriber::default_controls(),
+        }
+    }
+}
+
+impl From<SolutionV5> for Solution {
+    fn from(old: SolutionV5) -> Self {
+        let connector = |side, old: ConnectorV5| OmniversalConnector {
+            side,
+            offset: 0,
+            ports: old.ports,
+            slider: old.slider,
+        };
+        Solution {
+            level_key: old.level_key,
+            cables: old
+                .cables
+                .into_iter()
+                .map(|(pos, cable)| (pos, cable.into()))
+                .collect(),
+            connectors: vec![
+                connector(Direction4::West, old.left),
+                connector(Direction4::East, old.right),
+            ],
+            metrics: old.metrics,
+        }
+    }
+}
+
+impl From<CableV5> for Cable {
+    fn from(old: CableV5) -> Self {
+        match old {
+            CableV5::Straight { kind, horizontal } => Cable::Straight { kind, horizontal },
+            CableV5::Bent { kind, ccw_dir } => Cable::Bent { kind, ccw_dir },
+            // The vertical one was always drawn on top
+            CableV5::Crossover {
+                horiz_kind,
+                vert_kind,
+            } => Cable::Crossover {
+                horiz_kind,
+                vert_kind,
+                horiz_on_top: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        simulator::transport::Resource,
+        utils::profile::{
+            storage::{MemoryStorage, ProfileStorage},
+            ProfileStore, SERIALIZATION_VERSION,
+        },
+    };
+
+    #[test]
+    fn upgrades_from_5() {
+        let mut cables = AHashMap::new();
+        cables.insert(
+            ICoord::new(0, 1),
+            CableV5::Straight {
+                kind: CableKind::Pipe,
+                horizontal: true,
+            },
+        );
+        cables.insert(
+            ICoord::new(1, 1),
+            CableV5::Crossover {
+                horiz_kind: CableKind::Pipe,
+                vert_kind: CableKind::Wire,
+            },
+        );
+        let mut solutions = AHashMap::new();
+        solutions.insert(
+            "tutorial1".to_owned(),
+            SolutionV5 {
+                level_key: "tutorial1".to_owned(),
+                cables,
+                left: ConnectorV5 {
+                    ports: vec![None, Some(Port::Sink(Resource::Fuel)), None],
+                    slider: vec![false; 3],
+                },
+                right: ConnectorV5 {
+                    ports: vec![None, Some(Port::Source(Resource::Fuel)), None],
+                    slider: vec![false; 3],
+                },
+                metrics: Some(Metrics {
+                    total_cycles: 5,
+                    min_cycles: 5,
+                    crossovers: 1,
+                }),
+            },
+        );
+        let mut storage = MemoryStorage::default();
+        storage
+            .save("5", &bincode::serialize(&ProfileV5 { solutions }).unwrap())
+            .unwrap();
+
+        let mut store = ProfileStore::new(Box::new(storage.clone()));
+        let soln = &store.profile.solutions["tutorial1"];
+        assert!(matches!(
+            soln.cables[&ICoord::new(0, 1)],
+            Cable::Straight {
+                kind: CableKind::Pipe,
+                horizontal: true,
+            }
+        ));
+        assert!(matches!(
+            soln.cables[&ICoord::new(1, 1)],
+            Cable::Crossover {
+                horiz_kind: CableKind::Pipe,
+                vert_kind: CableKind::Wire,
+                horiz_on_top: false,
+            }
+        ));
+        assert_eq!(
+            soln.connectors,
+            vec![
+                OmniversalConnector {
+                    side: Direction4::West,
+                    offset: 0,
+                    ports: vec![None, Some(Port::Sink(Resource::Fuel)), None],
+                    slider: vec![false; 3],
+                },
+                OmniversalConnector {
+                    side: Direction4::East,
+                    offset: 0,
+                    ports: vec![None, Some(Port::Source(Resource::Fuel)), None],
+                    slider: vec![false; 3],
+                },
+            ]
+        );
+        assert_eq!(soln.metrics.as_ref().unwrap().crossovers, 1);
+        assert_eq!(store.profile.keymap, InputSubscriber::default_controls());
+
+        // It gets saved under the new version
+        store.tick(0);
+        assert!(storage.load(SERIALIZATION_VERSION).is_ok());
+    }
+}
@@ -0,0 +1,295 @@
+mod migrate;
+mod storage;
+
+use ahash::AHashMap;
+use anyhow::{anyhow, Context};
+use macroquad::prelude::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{Control, InputCode, InputSubscriber},
+    simulator::solutions::Solution,
+};
+
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use self::storage::{MemoryStorage, ProfileStorage};
+
+/// Change this whenever the profile's layout changes, and teach `migrate` to read the old one.
+/// The last released version was 5; 6 and 7 never shipped.
+const SERIALIZATION_VERSION: &str = "8";
+
+/// Save at most once every this many update frames,
+/// so a flurry of edits only gets saved once.
+const SAVE_INTERVAL: u64 = 30;
+
+static PROFILE: Lazy<Mutex<ProfileStore>> =
+    Lazy::new(|| Mutex::new(ProfileStore::new(default_storage())));
+
+/// Profile information. The `get` function borrows the one everything shares,
+/// and `tick` saves changes to it every so often.
+#[derive(Serialize, Deserialize)]
+pub struct Profile {
+    pub solutions: AHashMap<String, Solution>,
+    /// What inputs trigger what controls.
+    pub keymap: HashMap<InputCode, Control>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            solutions: AHashMap::new(),
+            keymap: InputSubscriber::default_controls(),
+        }
+    }
+}
+
+impl Profile {
+    /// Borrow the profile. Don't hold onto it; nothing else can get at it until it's dropped.
+    pub fn get() -> ProfileGuard {
+        ProfileGuard(PROFILE.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Save the profile if it's changed, unless it was saved very recently.
+    /// Call this every update frame.
+    pub fn tick(frames_ran: u64) {
+        Profile::get().0.tick(frames_ran);
+    }
+
+    /// Save the profile right now if it's changed, instead of waiting for `tick`.
+    /// Use this for changes that would hurt to lose, and when the game closes.
+    pub fn flush() {
+        Profile::get().0.flush();
+    }
+}
+
+/// The shared profile, borrowed from `Profile::get`.
+/// Changing anything through this marks the profile as needing a save.
+pub struct ProfileGuard(MutexGuard<'static, ProfileStore>);
+
+impl Deref for ProfileGuard {
+    type Target = Profile;
+
+    fn deref(&self) -> &Profile {
+        &self.0.profile
+    }
+}
+
+impl DerefMut for ProfileGuard {
+    fn deref_mut(&mut self) -> &mut Profile {
+        self.0.profile_mut()
+    }
+}
+
+/// The profile, where it's kept, and whether it needs saving.
+struct ProfileStore {
+    profile: Profile,
+    storage: Box<dyn ProfileStorage>,
+    /// Has the profile changed since it was last saved?
+    dirty: bool,
+    /// Update frame it was last saved on
+    last_save: Option<u64>,
+}
+
+impl ProfileStore {
+    fn new(storage: Box<dyn ProfileStorage>) -> Self {
+        let (profile, upgraded) = match ProfileStore::load(&*storage) {
+            Ok((mut it, upgraded)) => {
+                InputSubscriber::fill_in_default_controls(&mut it.keymap);
+                (it, upgraded)
+            }
+            Err(oh_no) => {
+                warn!("Couldn't load profile! Loading default...\n{:?}", oh_no);
+                (Profile::default(), false)
+            }
+        };
+        Self {
+            profile,
+            storage,
+            // An upgraded profile gets saved under the new version straight away
+            dirty: upgraded,
+            last_save: None,
+        }
+    }
+
+    /// Load the profile, upgrading it from an older version if there's nothing saved for this one.
+    /// Also return whether it was upgraded.
+    fn load(storage: &dyn ProfileStorage) -> anyhow::Result<(Profile, bool)> {
+        // note we save the raw bincode! the storage gzips it already!
+        // if we gzipped it here it would jut be gzipped twice
+        if let Ok(data) = storage.load(SERIALIZATION_VERSION) {
+            let profile = bincode::deserialize(&data).context("When deserializing from bincode")?;
+            return Ok((profile, false));
+        }
+        for &version in migrate::OLD_VERSIONS {
+            if let Ok(data) = storage.load(version) {
+                let profile = migrate::upgrade(version, &data)
+                    .with_context(|| format!("When upgrading from version {}", version))?;
+                info!("Upgraded the profile from version {}", version);
+                return Ok((profile, true));
+            }
+        }
+        Err(anyhow!("There's no profile saved yet"))
+    }
+
+    fn profile_mut(&mut self) -> &mut Profile {
+        self.dirty = true;
+        &mut self.profile
+    }
+
+    fn tick(&mut self, frames_ran: u64) {
+        let due = match self.last_save {
+            Some(last) => frames_ran >= last + SAVE_INTERVAL,
+            None => true,
+        };
+        if self.dirty && due {
+            self.save();
+            self.last_save = Some(frames_ran);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.dirty {
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        let res = bincode::serialize(&self.profile)
+            .context("When serializing to bincode")
+            .and_then(|data| self.storage.save(SERIALIZATION_VERSION, &data));
+        match res {
+            Ok(()) => self.dirty = false,
+            // Leave it dirty to try again next time
+            Err(oh_no) => warn!("Couldn't save profile!\n{:?}", oh_no),
+        }
+    }
+}
+
+/// Where the profile is kept on this platform.
+fn default_storage() -> Box<dyn ProfileStorage> {
+    if cfg!(test) {
+        return Box::new(MemoryStorage::default());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        Box::new(storage::LocalStorage)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match storage::FileStorage::new() {
+            Ok(it) => Box::new(it),
+            Err(oh_no) => {
+                warn!(
+                    "Can't save the profile anywhere! It'll be lost on exit.\n{:?}",
+                    oh_no
+                );
+                Box::new(MemoryStorage::default())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(level_key: &str) -> Solution {
+        Solution {
+            level_key: level_key.to_owned(),
+            cables: AHashMap::new(),
+            connectors: Vec::new(),
+            metrics: None,
+        }
+    }
+
+    #[test]
+    fn nothing_saved_loads_the_default() {
+        let store = ProfileStore::new(Box::new(MemoryStorage::default()));
+        assert!(store.profile.solutions.is_empty());
+        assert_eq!(store.profile.keymap, InputSubscriber::default_controls());
+    }
+
+    #[test]
+    fn saves_come_back() {
+        let storage = MemoryStorage::default();
+        let mut store = ProfileStore::new(Box::new(storage.clone()));
+        store
+            .profile_mut()
+            .solutions
+            .insert("level1".to_owned(), solution("level1"));
+        store.tick(0);
+
+        let store = ProfileStore::new(Box::new(storage));
+        assert!(store.profile.solutions.contains_key("level1"));
+    }
+
+    #[test]
+    fn reading_doesnt_save() {
+        let storage = MemoryStorage::default();
+        let mut store = ProfileStore::new(Box::new(storage.clone()));
+        assert!(store.profile.solutions.is_empty());
+        store.tick(0);
+        assert_eq!(storage.saves(), 0);
+    }
+
+    #[test]
+    fn edits_are_batched() {
+        let storage = MemoryStorage::default();
+        let mut store = ProfileStore::new(Box::new(storage.clone()));
+
+        // One drag committed every frame
+        for frame in 0..SAVE_INTERVAL {
+            let key = format!("level{}", frame);
+            store
+                .profile_mut()
+                .solutions
+                .insert(key.clone(), solution(&key));
+            store.tick(frame);
+        }
+        // The first one goes right away, and the rest wait
+        assert_eq!(storage.saves(), 1);
+
+        store.tick(SAVE_INTERVAL);
+        assert_eq!(storage.saves(), 2);
+        // Nothing new to save
+        store.tick(SAVE_INTERVAL * 2);
+        assert_eq!(storage.saves(), 2);
+
+        let store = ProfileStore::new(Box::new(storage));
+        assert_eq!(store.profile.solutions.len(), SAVE_INTERVAL as usize);
+    }
+
+    #[test]
+    fn flush_saves_whats_dirty() {
+        let storage = MemoryStorage::default();
+        let mut store = ProfileStore::new(Box::new(storage.clone()));
+        store
+            .profile_mut()
+            .solutions
+            .insert("level1".to_owned(), solution("level1"));
+        store.tick(0);
+        // Too soon after the last save for `tick` to save this
+        store
+            .profile_mut()
+            .solutions
+            .insert("level2".to_owned(), solution("level2"));
+        store.tick(1);
+        assert_eq!(storage.saves(), 1);
+
+        store.flush();
+        assert_eq!(storage.saves(), 2);
+        // Nothing new to save
+        store.flush();
+        assert_eq!(storage.saves(), 2);
+
+        let store = ProfileStore::new(Box::new(storage));
+        assert!(store.profile.solutions.contains_key("level2"));
+    }
+}
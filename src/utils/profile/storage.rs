@@ -0,0 +1,128 @@
+//! Places the profile can be kept between runs.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+use anyhow::{anyhow, Result};
+use quad_wasmnastics::storage::Location;
+
+/// Somewhere to keep the profile's bytes.
+///
+/// Each version of the save format is kept apart,
+/// so older versions' saves are still there to be upgraded.
+pub trait ProfileStorage: Send {
+    /// Get back what was saved last under this version. It's an error if nothing ever was.
+    fn load(&self, version: &str) -> Result<Vec<u8>>;
+    /// Replace what's saved under this version with this.
+    fn save(&mut self, version: &str, data: &[u8]) -> Result<()>;
+}
+
+/// The usual place for this version of the save format.
+fn location(version: &str) -> Location {
+    Location {
+        version: version.to_owned(),
+        ..Default::default()
+    }
+}
+
+/// Gzipped files in the data directory, one for each version.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    /// This fails if there's no data directory to put the files in.
+    pub fn new() -> Result<Self> {
+        dirs::data_dir().ok_or_else(|| anyhow!("Couldn't find the data directory"))?;
+        Ok(FileStorage)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ProfileStorage for FileStorage {
+    fn load(&self, version: &str) -> Result<Vec<u8>> {
+        use quad_wasmnastics::storage::flate;
+
+        let path = location(version).path()?;
+        let zipped = std::fs::read(&path)
+            .with_context(|| format!("When reading the file at {:?}", &path))?;
+        flate::unzip(&zipped)
+    }
+
+    fn save(&mut self, version: &str, data: &[u8]) -> Result<()> {
+        use quad_wasmnastics::storage::flate;
+
+        let path = location(version).path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, flate::zip(data)?)
+            .with_context(|| format!("When writing to the file at {:?}", &path))
+    }
+}
+
+/// The browser's localStorage.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+// On the web, wasmnastics always stores to localStorage
+#[cfg(target_arch = "wasm32")]
+impl ProfileStorage for LocalStorage {
+    fn load(&self, version: &str) -> Result<Vec<u8>> {
+        quad_wasmnastics::storage::load_from(&location(version))
+    }
+
+    fn save(&mut self, version: &str, data: &[u8]) -> Result<()> {
+        quad_wasmnastics::storage::save_to(data, &location(version))
+    }
+}
+
+/// Keeps the profile in memory and forgets it when the game closes.
+/// This is for tests, so they don't touch anyone's real save data.
+///
+/// Clones share the same data, so a test can hold onto one to look at what got saved.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    inner: Arc<Mutex<MemoryData>>,
+}
+
+#[derive(Default)]
+struct MemoryData {
+    /// What's saved under each version
+    data: HashMap<String, Vec<u8>>,
+    saves: usize,
+}
+
+impl MemoryStorage {
+    /// How many times this has been saved to.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn saves(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .saves
+    }
+}
+
+impl ProfileStorage for MemoryStorage {
+    fn load(&self, version: &str) -> Result<Vec<u8>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .data
+            .get(version)
+            .cloned()
+            .ok_or_else(|| anyhow!("Nothing has been saved under version {} yet", version))
+    }
+
+    fn save(&mut self, version: &str, data: &[u8]) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.data.insert(version.to_owned(), data.to_vec());
+        inner.saves += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{vec2, Camera2D, Vec2};
+
+use crate::{
+    boilerplates::RenderTargetStack,
+    controls::{Control, InputSubscriber},
+    utils::draw::mouse_position_pixel,
+    HEIGHT, WIDTH,
+};
+
+/// Zoom levels the mouse wheel steps between.
+/// Non-integer ones do make the pixels a little uneven, but it's that or not fitting.
+const ZOOMS: [f32; 6] = [0.5, 0.75, 1.0, 1.5, 2.0, 3.0];
+/// Index of 1x in `ZOOMS`
+const DEFAULT_ZOOM: usize = 2;
+
+/// How the board is zoomed and panned.
+///
+/// The board is drawn in "world" pixels, which are the same as canvas pixels
+/// at 1x zoom with nothing panned.
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    /// Index into `ZOOMS`
+    zoom_idx: usize,
+    /// World position in the middle of the canvas
+    center: Vec2,
+    /// World position under the mouse when panning started
+    grab: Option<Vec2>,
+}
+
+impl View {
+    /// Make a view zoomed out just far enough to fit something this big in pixels,
+    /// centered on the canvas.
+    pub fn fitting(width: f32, height: f32) -> Self {
+        let zoom_idx = (0..=DEFAULT_ZOOM)
+            .rev()
+            .find(|&idx| width * ZOOMS[idx] <= WIDTH && height * ZOOMS[idx] <= HEIGHT)
+            .unwrap_or(0);
+        Self {
+            zoom_idx,
+            center: canvas_center(),
+            grab: None,
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        ZOOMS[self.zoom_idx]
+    }
+
+    /// Zoom with the mouse wheel, around the mouse, and pan by dragging.
+    pub fn update(&mut self, controls: &InputSubscriber) {
+        let (mx, my) = mouse_position_pixel();
+        let mouse = vec2(mx, my);
+
+        let wheel = controls.wheel();
+        if wheel != 0.0 {
+            let (wx, wy) = self.to_world((mx, my));
+            self.zoom_idx = if wheel > 0.0 {
+                (self.zoom_idx + 1).min(ZOOMS.len() - 1)
+            } else {
+                self.zoom_idx.saturating_sub(1)
+            };
+            // Keep the same spot under the mouse
+            self.center = vec2(wx, wy) - (mouse - canvas_center()) / self.zoom();
+        }
+
+        if controls.pressed(Control::Pan) {
+            let (wx, wy) = self.to_world((mx, my));
+            match self.grab {
+                // Move so the grabbed spot is back under the mouse
+                Some(grab) => self.center += grab - vec2(wx, wy),
+                None => self.grab = Some(vec2(wx, wy)),
+            }
+        } else {
+            self.grab = None;
+        }
+    }
+
+    /// Turn a position on the canvas into a world position.
+    pub fn to_world(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let world = (vec2(x, y) - canvas_center()) / self.zoom() + self.center;
+        (world.x, world.y)
+    }
+
+    /// Turn a world position into a position on the canvas.
+    pub fn to_canvas(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let canvas = (vec2(x, y) - self.center) * self.zoom() + canvas_center();
+        (canvas.x, canvas.y)
+    }
+
+    /// Push a camera that draws through this view.
+    /// Pop it to go back to drawing straight onto the canvas.
+    pub fn push_camera(&self, render_targets: &mut RenderTargetStack) {
+        let top = *render_targets.get_stack().last().unwrap();
+        render_targets.push(Camera2D {
+            zoom: top.zoom * self.zoom(),
+            target: self.center,
+            ..top
+        });
+    }
+}
+
+fn canvas_center() -> Vec2 {
+    vec2(WIDTH / 2.0, HEIGHT / 2.0)
+}